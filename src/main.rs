@@ -26,19 +26,21 @@ async fn main() {
     match app_result {
         Ok(path) => {
             // with this the user can use navfs to cd to the location last browsed
-            args.get("file").map(|file| match File::create(file) {
-                Ok(mut f) => match writeln!(f, "{}", path) {
-                    Ok(_) => exit(0),
+            if let Some(file) = args.get("file") {
+                match File::create(file) {
+                    Ok(mut f) => match writeln!(f, "{}", path) {
+                        Ok(_) => exit(0),
+                        Err(e) => {
+                            eprintln!("Error writing to file: {}", e);
+                            exit(ExitCode::ErrorWritingToFile as i32);
+                        }
+                    },
                     Err(e) => {
-                        eprintln!("Error writing to file: {}", e);
-                        exit(ExitCode::ErrorWritingToFile as i32);
+                        eprintln!("Error creating file: {}", e);
+                        exit(ExitCode::ErrorCreatingFile as i32);
                     }
-                },
-                Err(e) => {
-                    eprintln!("Error creating file: {}", e);
-                    exit(ExitCode::ErrorCreatingFile as i32);
                 }
-            });
+            }
         }
         Err(e) => {
             eprintln!("Error running app: {}", e);
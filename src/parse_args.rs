@@ -14,7 +14,7 @@ pub fn parse_args() -> HashMap<String, String> {
         -f, --file  file to output final dir
         -h, --help    Show this message.
     "#,
-            args.get(0).unwrap_or(&"???".to_string())
+            args.first().unwrap_or(&"???".to_string())
         ];
 
         exit(0)
@@ -34,5 +34,5 @@ pub fn parse_args() -> HashMap<String, String> {
 
     args_map.insert("file".to_string(), file.to_string());
 
-    return args_map;
+    args_map
 }
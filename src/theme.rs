@@ -3,6 +3,12 @@ use ratatui::prelude::*;
 pub struct Theme {
     pub normal: Style,
     pub selected: Style,
+    pub matched: Style,
+    /// the numbers on the left of the text preview
+    pub line_number: Style,
+    /// styles of the highlighted code in the text preview, by the scopes they apply to. scopes
+    /// follow sublime text's naming, the one matching most specifically is used
+    pub code: Vec<(&'static str, Style)>,
 }
 
 // TODO: ability to configure colors and styles in a .file
@@ -14,6 +20,54 @@ impl Theme {
             selected: Style::default()
                 .fg(Color::Blue)
                 .add_modifier(Modifier::BOLD),
+            matched: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            line_number: Style::default().add_modifier(Modifier::DIM),
+            code: vec![
+                ("comment", Style::default().add_modifier(Modifier::DIM)),
+                ("string", Style::default().fg(Color::Green)),
+                (
+                    "constant.character.escape",
+                    Style::default().fg(Color::Cyan),
+                ),
+                ("constant", Style::default().fg(Color::Magenta)),
+                ("keyword, storage", Style::default().fg(Color::Blue)),
+                ("keyword.operator", Style::default()),
+                (
+                    "entity.name.function, support.function",
+                    Style::default().fg(Color::Yellow),
+                ),
+                (
+                    "entity.name.type, entity.name.class, support.type, support.class",
+                    Style::default().fg(Color::Cyan),
+                ),
+                ("entity.name.tag", Style::default().fg(Color::Blue)),
+                (
+                    "entity.other.attribute-name",
+                    Style::default().fg(Color::Yellow),
+                ),
+                ("variable.parameter", Style::default().fg(Color::Red)),
+                (
+                    "markup.heading",
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::BOLD),
+                ),
+                ("markup.bold", Style::default().add_modifier(Modifier::BOLD)),
+                (
+                    "markup.italic",
+                    Style::default().add_modifier(Modifier::ITALIC),
+                ),
+                ("markup.inserted", Style::default().fg(Color::Green)),
+                ("markup.deleted", Style::default().fg(Color::Red)),
+                (
+                    "invalid",
+                    Style::default()
+                        .fg(Color::Red)
+                        .add_modifier(Modifier::REVERSED),
+                ),
+            ],
         }
     }
 }
@@ -1,5 +1,7 @@
 //! Should handle only ui elements leaving actual file system implementations to the `Dir` type
 use std::io::{Result, Stdout};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 use crate::tui::Tui;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
@@ -11,9 +13,27 @@ use ratatui::{
     widgets::Widget,
 };
 
-use super::file_picker::{dir::Dir, file_picker::FilePicker};
+use crate::theme::Theme;
+
+use super::file_picker::{
+    dir::Dir,
+    file_picker::{FilePicker, Selection},
+    frecency::Frecency,
+    jobs::Jobs,
+};
+use super::pane_layout::pane_layout::PaneLayout;
+use super::popup::popup::{show_confirmation, show_info};
 use super::preview_pane::preview_pane::PreviewPane;
 
+/// how long quitting waits for cancelled jobs to clean up, a job stuck in a system call, like
+/// reading from a hung network mount, never stops
+const EXIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// how often the app wakes up without user input to show the progress of background jobs
+const TICK_RATE: Duration = Duration::from_millis(100);
+/// how often the app wakes up while a preview is being generated, to show it as soon as it's done
+const PREVIEW_TICK_RATE: Duration = Duration::from_millis(16);
+
 // to determine which panel is currently selected
 #[derive(Eq, PartialEq)]
 enum WhichPane {
@@ -24,15 +44,24 @@ enum WhichPane {
 /// the App module exists to manage states between child widgets
 /// it alsos handles global keybindings
 ///
-/// [file_picker](FilePicker): left side widget
+/// [tabs](Vec<FilePicker>): left side widget of each tab, with its own directory, cursor, filter
+/// and history
+/// [curr_tab](usize): the index of the tab being displayed
 /// [preview_pane](PreviewPane): right side widget
+/// [parent_column](FilePicker): the parent of the directory being displayed, in the miller layout
+/// [layout](PaneLayout): how the panels are arranged
 /// [curr_selected](WhichPane): enum to which panel is currently selected
+/// [jobs](Jobs): file system operations running in the background
 /// [exit](bool): if the app is done executing
 /// [term](Terminal<CrosstermBackend<Stdout>>): the virtual terminal running the app
 pub struct App<'a> {
-    file_picker: FilePicker,
+    tabs: Vec<FilePicker>,
+    curr_tab: usize,
     preview_pane: PreviewPane,
+    parent_column: FilePicker,
+    layout: PaneLayout,
     curr_selected: WhichPane,
+    jobs: Jobs,
     exit: bool,
     term: &'a mut Terminal<CrosstermBackend<Stdout>>,
 }
@@ -40,55 +69,114 @@ pub struct App<'a> {
 impl App<'_> {
     /// executes the app and returns the path to the final working directory
     pub async fn run(terminal: &mut Tui) -> Result<String> {
+        let jobs = Jobs::new();
+
         let mut app = App {
-            file_picker: FilePicker::new(true),
-            preview_pane: PreviewPane::new(),
+            tabs: vec![FilePicker::new(true, jobs.clone())],
+            curr_tab: 0,
+            preview_pane: PreviewPane::new(jobs.clone()),
+            parent_column: FilePicker::new(false, jobs.clone()),
+            layout: PaneLayout::Split,
             curr_selected: WhichPane::FilePicker,
+            jobs,
             exit: false,
             term: terminal,
         };
 
-        app.file_picker.initialize(None, None);
+        // the working directory is only read at the start, each tab keeps track of its own
+        let start_dir = Dir::get_cur_dir().pathbuf;
+        app.file_picker().initialize(Some(start_dir.clone()), None);
 
-        app.preview_pane.initialize(None);
+        app.preview_pane.initialize(start_dir);
+
+        // only draws when something changed, the preview pane is expensive to render
+        let mut needs_draw = true;
 
         while !app.exit {
-            // main render loop done inline to avoid borrows
-            // handles rendering and constructing of Widgets
-            // TODO: modularize Widget construction for better readability
-            app.term.draw(|frame| {
-                let _area = frame.size();
-                let buf: &mut Buffer = frame.buffer_mut();
-
-                // splits the screen into zones for each widget
-                let layout_main_statusbar = Layout::default()
-                    .direction(Direction::Vertical)
-                    // the bottom space has exactly 1 line of space the rest is filled by the panels
-                    .constraints(vec![Constraint::Fill(100), Constraint::Length(1)])
-                    .split(*buf.area());
-                let layout_picker_preview = Layout::default()
-                    .direction(Direction::Horizontal)
-                    // divides the panels area in 2
-                    .constraints(vec![Constraint::Percentage(40), Constraint::Percentage(60)])
-                    .split(layout_main_statusbar[0]);
-
-                app.file_picker.render(layout_picker_preview[0], buf);
-
-                app.preview_pane.render(layout_picker_preview[1], buf);
-
-                let status_bar_text = Dir::get_shortened_path(Dir::get_cur_dir().pathbuf);
-
-                Paragraph::new(status_bar_text)
-                    .add_modifier(Modifier::DIM)
-                    .render(layout_main_statusbar[1], buf);
-            })?;
-
-            // handle key inputs
-            app.handle_events()?;
+            if needs_draw {
+                app.update_parent_column();
+
+                // main render loop done inline to avoid borrows
+                // handles rendering and constructing of Widgets
+                // TODO: modularize Widget construction for better readability
+                app.term.draw(|frame| {
+                    let _area = frame.size();
+                    let buf: &mut Buffer = frame.buffer_mut();
+
+                    // the tab bar is only shown when there's more than one tab
+                    let tab_bar_height = if app.tabs.len() > 1 { 1 } else { 0 };
+
+                    // splits the screen into zones for each widget
+                    let layout_main_statusbar = Layout::default()
+                        .direction(Direction::Vertical)
+                        // the bottom space has exactly 1 line of space the rest is filled by the panels
+                        .constraints(vec![
+                            Constraint::Length(tab_bar_height),
+                            Constraint::Fill(100),
+                            Constraint::Length(1),
+                        ])
+                        .split(*buf.area());
+                    // divides the panels area between the panels of the layout chosen
+                    let areas = app.layout.split(layout_main_statusbar[1]);
+
+                    App::render_tab_bar(&app.tabs, app.curr_tab, layout_main_statusbar[0], buf);
+
+                    // the root has no parent to show
+                    if let Some(parent_area) = areas.parent {
+                        if app.tabs[app.curr_tab]
+                            .get_curr_displaying_dir()
+                            .parent()
+                            .is_some()
+                        {
+                            app.parent_column.render(parent_area, buf);
+                        }
+                    }
+
+                    app.tabs[app.curr_tab].render(areas.picker, buf);
+
+                    if let Some(preview_area) = areas.preview {
+                        app.preview_pane.render(preview_area, buf);
+                    }
+
+                    let status_bar_text =
+                        Dir::get_shortened_path(app.tabs[app.curr_tab].get_curr_displaying_dir());
+
+                    Paragraph::new(status_bar_text)
+                        .add_modifier(Modifier::DIM)
+                        .render(layout_main_statusbar[2], buf);
+
+                    // progress of the running job on the right side of the status bar
+                    if let Some(job_status) = app.jobs.get_status_line() {
+                        Paragraph::new(job_status)
+                            .alignment(Alignment::Right)
+                            .render(layout_main_statusbar[2], buf);
+                    }
+                })?;
+            }
+
+            // handle key inputs, waking up regularly to update the progress of background jobs
+            let tick_rate = match app.preview_pane.is_loading() {
+                true => PREVIEW_TICK_RATE,
+                false => TICK_RATE,
+            };
+            needs_draw = event::poll(tick_rate)?;
+            if needs_draw {
+                app.handle_events()?;
+            }
+            needs_draw |= app.handle_jobs();
+            needs_draw |= app.preview_pane.receive_preview();
+            needs_draw |= app.preview_pane.handle_fs_changes();
         }
 
-        // once the app finishes executing it returns the internal current directory
-        Ok(Dir::get_cur_dir().pathbuf.display().to_string())
+        // the visits are saved in the background a few seconds later, which won't happen anymore
+        Frecency::save_pending();
+
+        // once the app finishes executing it returns the directory of the current tab
+        Ok(app
+            .file_picker()
+            .get_curr_displaying_dir()
+            .display()
+            .to_string())
     }
 
     fn handle_events(&mut self) -> Result<()> {
@@ -96,20 +184,48 @@ impl App<'_> {
             // it's important to check that the event is a key press event as
             // crossterm also emits key release and repeat events on Windows.
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                if key_event.code == KeyCode::Char('q') {
+                // text typed in a search box is not a keybinding
+                if self.file_picker().is_typing() {
+                    let prev = self.file_picker().curr_sel_entry();
+                    self.file_picker().handle_keys(key_event);
+                    self.update_preview(prev);
+                } else if self.curr_selected == WhichPane::PreviewPane
+                    && self.preview_pane.is_typing()
+                {
+                    self.preview_pane.handle_keys(key_event);
+                } else if key_event.code == KeyCode::Char('q') {
                     self.exit();
+                } else if let KeyCode::Char(number @ '1'..='9') = key_event.code {
+                    // [1-9] switch to that tab, the number after the last one opens a new tab
+                    // the key after [b], [m] or ['] never gets here, the picker reads it itself
+                    self.switch_tab(number as usize - '1' as usize);
+                } else if key_event.code == KeyCode::Char('v') {
+                    // [v] cycle through the layouts
+                    self.switch_layout();
                 } else if let KeyModifiers::CONTROL = key_event.modifiers {
                     match key_event.code {
+                        // [Ctrl+c] cancel running and queued jobs
+                        KeyCode::Char('c') => self.jobs.cancel_all(),
+                        // [Ctrl+r] redo the last undone file operation
+                        KeyCode::Char('r') => self.jobs.redo(),
+                        // [Ctrl+w] close the current tab
+                        KeyCode::Char('w') => self.close_tab(),
+                        // [Ctrl+d/u] scroll the file previewed half a page down/up
+                        KeyCode::Char('d') | KeyCode::Char('u')
+                            if matches!(self.curr_selected, WhichPane::PreviewPane) =>
+                        {
+                            self.preview_pane.handle_keys(key_event)
+                        }
                         // [Ctrl+l] switch to preview pane
-                        KeyCode::Char('l') => {
+                        KeyCode::Char('l') if self.layout.has_preview() => {
                             self.curr_selected = WhichPane::PreviewPane;
-                            self.file_picker.active = false;
+                            self.file_picker().active = false;
                             self.preview_pane.active = true;
                         }
                         // [Ctrl+h] switch to file picker
                         KeyCode::Char('h') => {
                             self.curr_selected = WhichPane::FilePicker;
-                            self.file_picker.active = true;
+                            self.file_picker().active = true;
                             self.preview_pane.active = false;
                         }
                         _ => (),
@@ -117,24 +233,15 @@ impl App<'_> {
                 } else {
                     match self.curr_selected {
                         WhichPane::FilePicker => {
-                            self.file_picker.handle_keys(key_event);
-                            match key_event.code {
-                                KeyCode::Char('h')
-                                | KeyCode::Char('j')
-                                | KeyCode::Char('k')
-                                | KeyCode::Char('l') => {
-                                    let curr = self.file_picker.curr_sel_entry();
-                                    self.preview_pane.initialize(Some(curr));
-                                }
-                                _ => (),
-                            }
+                            let prev = self.file_picker().curr_sel_entry();
+                            self.file_picker().handle_keys(key_event);
+                            // if the user pressses a key that makes it necessary to update the
+                            // preview panel it does so. important that this happens after the
+                            // child panel has handled their events.
+                            self.update_preview(prev);
                         }
                         WhichPane::PreviewPane => self.preview_pane.handle_keys(key_event),
                     }
-
-                    // if the user pressses a key that makes it necessary to update the preview
-                    // panel it does so. important that this happens after the child panel has
-                    // handled their events.
                 }
             }
             _ => {}
@@ -148,16 +255,182 @@ impl App<'_> {
     /// manually by using the resize method. specially important after a popup.
     /// TODO: investigate a better way of handling this
     fn redraw_if_needed(&mut self) {
-        if self.file_picker.needs_redraw | self.preview_pane.needs_redraw {
+        if self.file_picker().needs_redraw | self.preview_pane.needs_redraw {
             if let Ok(size) = self.term.size() {
-                if let Ok(..) = self.term.resize(size) {};
+                let _ = self.term.resize(size);
             }
-            self.file_picker.needs_redraw = false;
+            self.file_picker().needs_redraw = false;
             self.preview_pane.needs_redraw = false;
         }
     }
 
+    /// previews the entry under the cursor if it's not [prev](PathBuf) anymore
+    /// returns `true` if the preview changed
+    fn update_preview(&mut self, prev: PathBuf) -> bool {
+        let curr = self.file_picker().curr_sel_entry();
+        let changed = curr != prev;
+        if changed {
+            self.preview_pane.initialize(curr);
+        }
+        changed
+    }
+
+    /// arranges the panels in the next layout
+    /// the file picker is selected if the preview pane is not part of the new layout
+    fn switch_layout(&mut self) {
+        self.layout = self.layout.next();
+        if !self.layout.has_preview() {
+            self.curr_selected = WhichPane::FilePicker;
+            self.file_picker().active = true;
+            self.preview_pane.active = false;
+        }
+    }
+
+    /// lists the parent of the directory being displayed in the parent column, with the cursor on
+    /// the directory being displayed. only needed in the miller layout
+    fn update_parent_column(&mut self) {
+        if self.layout != PaneLayout::Miller {
+            return;
+        }
+        let dir = self.file_picker().get_curr_displaying_dir();
+        let Some(parent) = dir.parent() else {
+            return;
+        };
+        // a new picker has no entries yet, so its directory is checked first
+        if self.parent_column.get_curr_displaying_dir() != parent
+            || self.parent_column.curr_sel_entry() != dir
+        {
+            self.parent_column
+                .initialize(Some(parent.to_path_buf()), Some(Selection::Entry(dir)));
+        }
+    }
+
+    fn file_picker(&mut self) -> &mut FilePicker {
+        &mut self.tabs[self.curr_tab]
+    }
+
+    /// shows the tab at [index](usize), or opens a new tab in the directory being displayed if
+    /// [index](usize) is right after the last tab. tabs are numbered in order, so indexes further
+    /// than that are ignored
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.curr_tab || index > self.tabs.len() {
+            return;
+        }
+
+        if index == self.tabs.len() {
+            let dir = self.file_picker().get_curr_displaying_dir();
+            let mut tab = FilePicker::new(false, self.jobs.clone());
+            tab.initialize(Some(dir), None);
+            self.tabs.push(tab);
+            self.show_tab(self.tabs.len() - 1);
+        } else {
+            self.show_tab(index);
+        }
+    }
+
+    /// closes the current tab, unless it's the only one
+    fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            return;
+        }
+        self.tabs.remove(self.curr_tab);
+        self.show_tab(self.curr_tab.min(self.tabs.len() - 1));
+    }
+
+    /// makes the tab at [index](usize) the current one
+    fn show_tab(&mut self, index: usize) {
+        self.curr_tab = index;
+        let is_picker_selected = self.curr_selected == WhichPane::FilePicker;
+        for (tab_index, tab) in self.tabs.iter_mut().enumerate() {
+            tab.active = tab_index == index && is_picker_selected;
+        }
+
+        let curr = self.file_picker().curr_sel_entry();
+        self.preview_pane.initialize(curr);
+    }
+
+    /// the number and directory name of every tab in [tabs](Vec<FilePicker>), the one at
+    /// [curr_tab](usize) highlighted
+    fn render_tab_bar(tabs: &[FilePicker], curr_tab: usize, area: Rect, buf: &mut Buffer) {
+        if area.height == 0 {
+            return;
+        }
+        let style = Theme::default();
+
+        let spans: Vec<Span> = tabs
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let dir = tab.get_curr_displaying_dir();
+                let name = match dir.file_name() {
+                    Some(name) => name.to_string_lossy().to_string(),
+                    None => dir.display().to_string(), // the root has no name
+                };
+                let text = " ".to_string() + &(index + 1).to_string() + " " + &name + " ";
+                match index == curr_tab {
+                    true => Span::styled(text, style.selected.add_modifier(Modifier::REVERSED)),
+                    false => Span::styled(text, Style::default().add_modifier(Modifier::DIM)),
+                }
+            })
+            .collect();
+
+        Line::from(spans).render(area, buf);
+    }
+
+    /// updates the widgets with the results of finished jobs
+    /// returns `true` if the screen needs to be redrawn to show job progress or results
+    fn handle_jobs(&mut self) -> bool {
+        let reports = self.jobs.take_finished();
+
+        for report in reports.iter() {
+            let prev = self.file_picker().curr_sel_entry();
+            // the job may have changed the directories of the other tabs too
+            for tab in self.tabs.iter_mut() {
+                tab.job_finished(report);
+            }
+            self.parent_column.job_finished(report);
+            if !self.update_preview(prev) {
+                self.preview_pane.job_finished(report);
+            }
+
+            if !report.errors.is_empty() {
+                show_info(
+                    &("Error ".to_string() + &report.description.to_lowercase()),
+                    report.get_errors_to_display(),
+                );
+                self.file_picker().needs_redraw = true;
+            }
+        }
+
+        self.redraw_if_needed();
+
+        !reports.is_empty() || !self.jobs.is_idle()
+    }
+
+    /// asks for confirmation before quitting with jobs still running, then cancels them and
+    /// waits for them to clean up so no half copied files are left behind
+    /// jobs that don't stop within [EXIT_TIMEOUT] are left behind if the user still wants to quit
     fn exit(&mut self) {
+        if !self.jobs.is_idle() {
+            self.file_picker().needs_redraw = true;
+            if !show_confirmation("Jobs still running", "Cancel them and quit?".to_string()) {
+                return;
+            }
+            self.jobs.cancel_all();
+            let deadline = Instant::now() + EXIT_TIMEOUT;
+            while !self.jobs.is_idle() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            if !self.jobs.is_idle()
+                && !show_confirmation(
+                    "Jobs still running",
+                    "Some jobs didn't stop, they may leave half copied files behind. Quit anyway?"
+                        .to_string(),
+                )
+            {
+                return;
+            }
+        }
         self.exit = true;
     }
 }
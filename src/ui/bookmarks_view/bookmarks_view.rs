@@ -0,0 +1,201 @@
+//! Full screen list of the bookmarks, where they can be deleted, reordered or jumped to
+//!
+//! Bookmarks whose directory doesn't exist anymore are kept and marked as missing, the directory
+//! may just be on a drive that isn't mounted. Every change is saved right away.
+//!
+//! # Example
+//! ```rust
+//! if let Some(path) = BookmarksView::show() {
+//!     file_picker.initialize(Some(path), None);
+//! }
+//! ```
+
+use std::{io::Result, path::PathBuf};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    prelude::*,
+    widgets::{block::Position, Block, List, ListState, Paragraph},
+};
+
+use crate::{
+    theme::Theme,
+    tui,
+    ui::{
+        file_picker::{bookmarks::Bookmarks, dir::Dir},
+        popup::popup::show_info,
+    },
+};
+
+/// [bookmarks](Bookmarks): the bookmarks being listed
+/// [index](usize): the index of the bookmark under the cursor
+/// [exit](bool): if the view should close
+pub struct BookmarksView {
+    bookmarks: Bookmarks,
+    index: usize,
+    exit: bool,
+}
+
+impl BookmarksView {
+    /// shows the bookmarks until the user closes the view or picks one, whose directory is
+    /// returned
+    pub fn show() -> Option<PathBuf> {
+        let bookmarks = match Bookmarks::load() {
+            Ok(bookmarks) => bookmarks,
+            Err(error) => {
+                show_info("Error reading bookmarks", error.to_string());
+                return None;
+            }
+        };
+
+        let mut view = BookmarksView {
+            bookmarks,
+            index: 0,
+            exit: false,
+        };
+
+        // try catch
+        view.run().unwrap_or_else(|error| {
+            show_info("Error displaying bookmarks", error.to_string());
+            None
+        })
+    }
+
+    fn run(&mut self) -> Result<Option<PathBuf>> {
+        let mut term = tui::init()?;
+        // the popups draw over the view, so it has to be drawn from scratch after them
+        term.clear()?;
+
+        while !self.exit {
+            term.draw(|frame| self.render(frame.size(), frame.buffer_mut()))?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Enter | KeyCode::Char('l') => {
+                        if let Some(path) = self.get_selected_dir() {
+                            return Ok(Some(path));
+                        }
+                        term.clear()?;
+                    }
+                    code => {
+                        if self.handle_keys(code) {
+                            term.clear()?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// returns `true` if a popup was shown
+    fn handle_keys(&mut self, key: KeyCode) -> bool {
+        let len = self.bookmarks.get_items().len();
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => self.exit = true,
+            KeyCode::Char('j') => self.index = (self.index + 1).min(len.max(1) - 1),
+            KeyCode::Char('k') => self.index = self.index.saturating_sub(1),
+            KeyCode::Char('g') => self.index = 0,
+            KeyCode::Char('G') => self.index = len.max(1) - 1,
+            KeyCode::Char('d') => {
+                self.bookmarks.remove(self.index);
+                self.index = self.index.min(len.saturating_sub(2));
+                return self.save();
+            }
+            // [J]/[K] move the bookmark under the cursor down/up the list
+            KeyCode::Char('J') if self.index + 1 < len => {
+                self.bookmarks.swap(self.index, self.index + 1);
+                self.index += 1;
+                return self.save();
+            }
+            KeyCode::Char('K') if self.index > 0 => {
+                self.bookmarks.swap(self.index, self.index - 1);
+                self.index -= 1;
+                return self.save();
+            }
+            _ => (),
+        }
+        false
+    }
+
+    /// returns the directory of the bookmark under the cursor, if it still exists
+    fn get_selected_dir(&self) -> Option<PathBuf> {
+        let bookmark = self.bookmarks.get_items().get(self.index)?;
+        if bookmark.path.is_dir() {
+            return Some(bookmark.path.clone());
+        }
+        show_info(
+            "Can't open bookmark",
+            Dir::get_shortened_path(bookmark.path.clone()) + " doesn't exist",
+        );
+        None
+    }
+
+    /// returns `true` if saving failed and the error was shown
+    fn save(&self) -> bool {
+        match self.bookmarks.save() {
+            Ok(()) => false,
+            Err(error) => {
+                show_info("Error saving bookmarks", error.to_string());
+                true
+            }
+        }
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let style = Theme::default();
+
+        let block = Block::bordered()
+            .title(" Bookmarks ")
+            .title(Line::from(" [Enter] open  [d]elete  [J/K] move down/up  [q] close ").centered())
+            .title_position(Position::Top);
+
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let items = self.bookmarks.get_items();
+        if items.is_empty() {
+            Paragraph::new("No bookmarks, press m and a key to bookmark a directory")
+                .add_modifier(Modifier::DIM)
+                .render(inner, buf);
+            return;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Fill(1), Constraint::Length(9)])
+            .split(inner);
+
+        let paths = items.iter().map(|bookmark| {
+            " ".to_string()
+                + &bookmark.key.to_string()
+                + "  "
+                + &Dir::get_shortened_path(bookmark.path.clone())
+        });
+
+        let missing = items.iter().map(|bookmark| match bookmark.path.is_dir() {
+            true => Line::from(""),
+            false => Line::styled("missing", Style::default().fg(Color::Red)),
+        });
+
+        let mut state = ListState::default().with_selected(Some(self.index));
+
+        StatefulWidget::render(
+            List::new(paths)
+                .style(style.normal)
+                .highlight_style(style.selected),
+            layout[0],
+            buf,
+            &mut state,
+        );
+
+        let mut state = ListState::default().with_selected(Some(self.index));
+
+        StatefulWidget::render(List::new(missing), layout[1], buf, &mut state);
+    }
+}
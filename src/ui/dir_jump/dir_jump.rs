@@ -0,0 +1,219 @@
+//! Popup to jump to a directory visited before, ranked by frecency
+//!
+//! The directories matching what is typed are listed as the user types, the most frequently and
+//! recently visited first. See [Frecency] for how they are ranked and matched.
+//!
+//! # Example
+//! ```rust
+//! if let Some(path) = DirJump::show(curr_dir.clone()) {
+//!     file_picker.initialize(Some(path), None);
+//! }
+//! ```
+
+use std::{
+    io::Result,
+    path::{Path, PathBuf},
+};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    prelude::*,
+    widgets::{Block, Clear, List, ListState, Paragraph},
+};
+
+use crate::{
+    theme::Theme,
+    tui,
+    ui::{
+        file_picker::{dir::Dir, frecency::Frecency},
+        popup::popup::show_info,
+        text_input::text_input::TextInput,
+        utils::Utils,
+    },
+};
+
+/// the popup doesn't grow past this, even on big terminals
+const MAX_WIDTH: u16 = 100;
+const MAX_HEIGHT: u16 = 20;
+
+/// [frecency](Frecency): the directories visited
+/// [exclude](PathBuf): the directory the user is in, not worth jumping to
+/// [input](TextInput): where the query is typed
+/// [results](Vec<PathBuf>): directories matching the query, best first
+/// [index](usize): the index of the result under the cursor
+pub struct DirJump {
+    frecency: Frecency,
+    exclude: PathBuf,
+    input: TextInput,
+    results: Vec<PathBuf>,
+    index: usize,
+}
+
+impl DirJump {
+    /// shows the popup until the user picks a directory, which is returned, or closes it
+    /// [curr_dir](PathBuf) is left out of the results
+    pub fn show(curr_dir: PathBuf) -> Option<PathBuf> {
+        let frecency = match Frecency::load() {
+            Ok(frecency) => frecency,
+            Err(error) => {
+                show_info("Error reading visited directories", error.to_string());
+                return None;
+            }
+        };
+
+        let mut jump = DirJump {
+            frecency,
+            exclude: curr_dir,
+            input: TextInput::new(""),
+            results: Vec::new(),
+            index: 0,
+        };
+        jump.update_results();
+
+        // try catch
+        jump.run().unwrap_or_else(|error| {
+            show_info("Error displaying jump", error.to_string());
+            None
+        })
+    }
+
+    fn run(&mut self) -> Result<Option<PathBuf>> {
+        let mut term = tui::init()?;
+
+        loop {
+            term.draw(|frame| self.render(frame.size(), frame.buffer_mut()))?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Enter => return Ok(self.results.get(self.index).cloned()),
+                    _ => self.handle_keys(key),
+                }
+            }
+        }
+    }
+
+    fn handle_keys(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Down => self.select_next(),
+            KeyCode::Up => self.select_prev(),
+            KeyCode::Char('n') | KeyCode::Char('j') if ctrl => self.select_next(),
+            KeyCode::Char('p') | KeyCode::Char('k') if ctrl => self.select_prev(),
+            _ => {
+                if self.input.handle_keys(key) {
+                    self.update_results();
+                }
+            }
+        }
+    }
+
+    fn select_next(&mut self) {
+        self.index = (self.index + 1).min(self.results.len().max(1) - 1);
+    }
+
+    fn select_prev(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    fn update_results(&mut self) {
+        self.results = self
+            .frecency
+            .query(&self.input.get_value(), &self.exclude)
+            .into_iter()
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.index = 0;
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let style = Theme::default();
+
+        let width = MAX_WIDTH.min(area.width.saturating_sub(4));
+        let height = MAX_HEIGHT.min(area.height.saturating_sub(2));
+        let area = Utils::centered_rect(width, height, area);
+
+        let block = Block::bordered()
+            .title(Line::from(" Jump to a visited directory ").centered())
+            .title_bottom(Line::from(" [Enter] open  [Up/Down] move  [Esc] close ").centered())
+            .title_style(
+                Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(Color::White),
+            );
+
+        // the popup is drawn by a new terminal that doesn't know what is under it, so the cells
+        // need a style for the blank ones to be actually drawn over the old content
+        Clear.render(area, buf);
+        Block::default()
+            .style(Style::default().fg(Color::White))
+            .render(area, buf);
+
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1), Constraint::Fill(1)])
+            .split(inner);
+
+        let input_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Length(2), Constraint::Fill(1)])
+            .split(layout[0]);
+
+        Paragraph::new("> ").render(input_layout[0], buf);
+        self.input.render(input_layout[1], buf);
+
+        if self.results.is_empty() {
+            Paragraph::new("No visited directory matches")
+                .add_modifier(Modifier::DIM)
+                .render(layout[1], buf);
+            return;
+        }
+
+        let keywords = Frecency::get_keywords(&self.input.get_value());
+        let lines: Vec<Line> = self
+            .results
+            .iter()
+            .map(|path| DirJump::get_line(path, &keywords, style.matched))
+            .collect();
+
+        let mut state = ListState::default().with_selected(Some(self.index));
+
+        StatefulWidget::render(
+            List::new(lines)
+                .style(style.normal)
+                .highlight_style(style.selected),
+            layout[1],
+            buf,
+            &mut state,
+        );
+    }
+
+    /// the shortened [path](Path) with the [keywords](Vec<String>) highlighted, if they're all in
+    /// the shortened path
+    fn get_line(path: &Path, keywords: &[String], matched: Style) -> Line<'static> {
+        let text = Dir::get_shortened_path(path.to_path_buf());
+        let positions = Frecency::find_keywords(&text, keywords).unwrap_or_default();
+
+        Line::from(
+            text.chars()
+                .enumerate()
+                .map(|(index, c)| {
+                    match positions
+                        .iter()
+                        .any(|(start, len)| (*start..start + len).contains(&index))
+                    {
+                        true => Span::styled(c.to_string(), matched),
+                        false => Span::raw(c.to_string()),
+                    }
+                })
+                .collect::<Vec<Span>>(),
+        )
+    }
+}
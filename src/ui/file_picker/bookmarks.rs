@@ -0,0 +1,117 @@
+//! Directories bookmarked under a key, kept between sessions
+//!
+//! Bookmarks are saved in `$XDG_DATA_HOME/navfs/bookmarks`, one per line as the key, a tab and the
+//! path, in the order they are listed. The file is read again every time it's needed, so
+//! bookmarks set in another instance show up right away.
+//!
+//! # Example
+//! ```rust
+//! let mut bookmarks = Bookmarks::load()?;
+//! bookmarks.set('p', PathBuf::from("/home/user/projects"));
+//! bookmarks.save()?;
+//!
+//! if let Some(bookmark) = Bookmarks::load()?.get('p') {
+//!     file_picker.initialize(Some(bookmark.path.clone()), None);
+//! }
+//! ```
+
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+};
+
+use crate::ui::utils::Utils;
+
+/// name of the file in `$XDG_DATA_HOME/navfs`
+const BOOKMARKS_FILE: &str = "bookmarks";
+
+/// a directory bookmarked under [key](char)
+///
+/// [key](char): typed after `'` to jump to the directory
+/// [path](PathBuf): absolute path of the directory, it may not exist anymore
+pub struct Bookmark {
+    pub key: char,
+    pub path: PathBuf,
+}
+
+/// [items](Vec<Bookmark>): the bookmarks, in the order they are listed
+pub struct Bookmarks {
+    items: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// reads the saved bookmarks, there are none if the file doesn't exist yet
+    /// malformed lines are ignored
+    pub fn load() -> Result<Bookmarks> {
+        let content = match fs::read_to_string(Utils::get_data_file(BOOKMARKS_FILE)) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+
+        let items = content
+            .lines()
+            .filter_map(|line| {
+                let (key, path) = line.split_once('\t')?;
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(key), None) if !path.is_empty() => Some(Bookmark {
+                        key,
+                        path: PathBuf::from(path),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Ok(Bookmarks { items })
+    }
+
+    /// writes the bookmarks to the file
+    pub fn save(&self) -> Result<()> {
+        let mut content = String::new();
+        for bookmark in &self.items {
+            let path = bookmark.path.to_string_lossy();
+            if path.contains('\n') {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "can't bookmark a path with a line break",
+                ));
+            }
+            content = content + &bookmark.key.to_string() + "\t" + &path + "\n";
+        }
+
+        Utils::write_data_file(BOOKMARKS_FILE, &content)
+    }
+
+    pub fn get_items(&self) -> &[Bookmark] {
+        &self.items
+    }
+
+    pub fn get(&self, key: char) -> Option<&Bookmark> {
+        self.items.iter().find(|bookmark| bookmark.key == key)
+    }
+
+    /// bookmarks [path](PathBuf) under [key](char), replacing what was there keeping its place in
+    /// the list. new keys go at the end
+    pub fn set(&mut self, key: char, path: PathBuf) {
+        match self.items.iter_mut().find(|bookmark| bookmark.key == key) {
+            Some(bookmark) => bookmark.path = path,
+            None => self.items.push(Bookmark { key, path }),
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.items.remove(index);
+        }
+    }
+
+    /// swaps the bookmarks at [a](usize) and [b](usize), to reorder the list
+    pub fn swap(&mut self, a: usize, b: usize) {
+        if a < self.items.len() && b < self.items.len() {
+            self.items.swap(a, b);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Renames many entries at once by editing their names in the user's `$EDITOR`
+//!
+//! The names are written one per line to a temporary file. Once the editor closes, each line is
+//! the new name of the entry in the same position. Renames that would swap names around (a→b,
+//! b→a) go through temporary names, so no entry is overwritten in the middle of the process.
+//!
+//! # Example
+//! ```rust
+//! let names = entries.iter().map(|entry| Dir::get_entry_name(entry.clone())).collect();
+//!
+//! if let Some(new_names) = BulkRename::edit_names(&names)? {
+//!     let renames = BulkRename::plan(&entries, &new_names)?;
+//!     let (changes, errors) = BulkRename::apply(&renames);
+//! }
+//! ```
+
+use std::{
+    collections::hash_map::RandomState,
+    env,
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{Error, ErrorKind, Result, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use itertools::Itertools;
+
+use crate::tui;
+
+use super::{dir::Dir, journal::Change};
+
+pub struct BulkRename {}
+
+impl BulkRename {
+    /// opens [names](Vec<String>) in the user's editor and returns the edited lines
+    /// returns `None` if the editor exited with an error, which is taken as the user giving up
+    pub fn edit_names(names: &[String]) -> Result<Option<Vec<String>>> {
+        let (file, mut writer) = BulkRename::create_temp_file()?;
+        if let Err(error) = writer.write_all((names.join("\n") + "\n").as_bytes()) {
+            let _ = fs::remove_file(&file);
+            return Err(error);
+        }
+        drop(writer);
+
+        // `$EDITOR` may come with arguments, like `code --wait`
+        let editor = env::var("EDITOR").unwrap_or("vi".to_string());
+        let mut args = editor.split_whitespace();
+        let program = args.next().unwrap_or("vi");
+
+        // the editor takes over the terminal until it exits
+        tui::restore()?;
+        let status = Command::new(program).args(args).arg(&file).status();
+        tui::init()?;
+
+        let res = match status {
+            Ok(status) if status.success() => fs::read_to_string(&file)
+                .map(|content| Some(content.lines().map(|line| line.to_string()).collect())),
+            Ok(_) => Ok(None),
+            Err(error) => Err(Error::new(
+                error.kind(),
+                "couldn't run ".to_string() + program + ": " + &error.to_string(),
+            )),
+        };
+
+        let _ = fs::remove_file(&file);
+        res
+    }
+
+    /// creates a new file in the temporary directory that only the user can read and write
+    ///
+    /// the name is random and the file must not exist yet, so nobody else can guess it and put a
+    /// symlink there to have the names written somewhere else
+    fn create_temp_file() -> Result<(PathBuf, File)> {
+        for _ in 0..16 {
+            let random = RandomState::new().build_hasher().finish();
+            let path = env::temp_dir().join(format!(
+                "navfs-rename-{}-{:016x}.txt",
+                process::id(),
+                random
+            ));
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)
+            {
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+                res => return res.map(|file| (path, file)),
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "couldn't find a free name for the temporary file",
+        ))
+    }
+
+    /// pairs every entry with its new path, leaving out the ones whose name didn't change
+    /// returns why the renames can't be done if the names are not valid
+    pub fn plan(
+        entries: &[PathBuf],
+        names: &[String],
+    ) -> std::result::Result<Vec<(PathBuf, PathBuf)>, String> {
+        // editors usually add a line break at the end, which becomes an empty last line
+        let names: Vec<&String> = match names.last() {
+            Some(last) if last.is_empty() && names.len() == entries.len() + 1 => {
+                names[..entries.len()].iter().collect()
+            }
+            _ => names.iter().collect(),
+        };
+
+        if names.len() != entries.len() {
+            return Err(format!(
+                "{} entries but {} names, lines can't be added or removed",
+                entries.len(),
+                names.len()
+            ));
+        }
+
+        let mut renames: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+        for (entry, name) in entries.iter().zip(names) {
+            if name.is_empty() {
+                return Err(Dir::get_entry_name(entry.clone()) + ": name can't be empty");
+            } else if name.contains('/') {
+                return Err(name.to_string() + ": name can't contain '/'");
+            } else if name == "." || name == ".." {
+                return Err(name.to_string() + ": name can't be '.' or '..'");
+            }
+
+            let dest = entry.parent().unwrap_or(Path::new("/")).join(name);
+            if dest != *entry {
+                renames.push((entry.clone(), dest));
+            }
+        }
+
+        if let Some((_, dest)) = renames.iter().duplicates_by(|(_, dest)| dest).next() {
+            return Err(Dir::get_entry_name(dest.clone()) + ": more than one entry renamed to it");
+        }
+
+        // names of entries that also get renamed are free by the time they are used
+        for (_, dest) in renames.iter() {
+            if dest.symlink_metadata().is_ok() && !renames.iter().any(|(src, _)| src == dest) {
+                return Err(Dir::get_entry_name(dest.clone()) + ": already exists");
+            }
+        }
+
+        Ok(renames)
+    }
+
+    /// `true` if some entry is renamed to the current name of another one, like in a→b, b→c or in
+    /// cycles like a→b, b→a
+    fn has_chains(renames: &[(PathBuf, PathBuf)]) -> bool {
+        renames
+            .iter()
+            .any(|(_, dest)| renames.iter().any(|(src, _)| src == dest))
+    }
+
+    /// lists the renames, one old and new name pair per entry
+    pub fn get_diff_to_display(renames: &[(PathBuf, PathBuf)]) -> String {
+        renames
+            .iter()
+            .map(|(src, dest)| {
+                "- ".to_string()
+                    + &Dir::get_shortened_path(src.clone())
+                    + "\n+ "
+                    + &Dir::get_shortened_path(dest.clone())
+            })
+            .join("\n")
+    }
+
+    /// renames the entries, returning the changes done and the entries that failed
+    /// when entries take names of each other they are all moved to temporary names first
+    pub fn apply(renames: &[(PathBuf, PathBuf)]) -> (Vec<Change>, Vec<(PathBuf, Error)>) {
+        let mut changes: Vec<Change> = Vec::new();
+        let mut errors: Vec<(PathBuf, Error)> = Vec::new();
+
+        // each step goes from the first path to the second, the third is where the entry was
+        let steps: Vec<(PathBuf, PathBuf, PathBuf)> = match BulkRename::has_chains(renames) {
+            false => renames
+                .iter()
+                .map(|(src, dest)| (src.clone(), dest.clone(), src.clone()))
+                .collect(),
+            true => {
+                let mut pending = Vec::new();
+                for (index, (src, dest)) in renames.iter().enumerate() {
+                    let temp = BulkRename::get_temp_name(src, index);
+                    match BulkRename::rename(src, &temp) {
+                        Ok(_) => {
+                            changes.push(Change::Moved {
+                                from: src.clone(),
+                                to: temp.clone(),
+                            });
+                            pending.push((temp, dest.clone(), src.clone()));
+                        }
+                        Err(error) => errors.push((src.clone(), error)),
+                    }
+                }
+                pending
+            }
+        };
+
+        for (src, dest, original) in steps {
+            match BulkRename::rename(&src, &dest) {
+                Ok(_) => changes.push(Change::Moved {
+                    from: src,
+                    to: dest,
+                }),
+                Err(error) => {
+                    // an entry left with a temporary name goes back to the one it had
+                    if src != original && BulkRename::rename(&src, &original).is_ok() {
+                        changes.push(Change::Moved {
+                            from: src,
+                            to: original.clone(),
+                        });
+                    }
+                    errors.push((original, error));
+                }
+            }
+        }
+
+        (changes, errors)
+    }
+
+    /// renames without overwriting, since [fs::rename] silently replaces files
+    fn rename(src: &Path, dest: &Path) -> Result<()> {
+        if dest.symlink_metadata().is_ok() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                Dir::get_entry_name(dest.to_path_buf()) + " already exists",
+            ));
+        }
+        fs::rename(src, dest)
+    }
+
+    /// returns a hidden name, next to [path](Path), not used by any entry
+    fn get_temp_name(path: &Path, index: usize) -> PathBuf {
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        let mut attempt = 0;
+        loop {
+            let temp = parent.join(format!(".navfs-rename-{}-{index}-{attempt}", process::id()));
+            if temp.symlink_metadata().is_err() {
+                return temp;
+            }
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn plan_leaves_out_unchanged_names_and_the_last_empty_line() {
+        let dir = TestDir::new("rename-plan");
+        let a = dir.write("a", "");
+        let b = dir.write("b", "");
+
+        let renames = BulkRename::plan(&[a.clone(), b], &names(&["c", "b", ""])).unwrap();
+
+        assert_eq!(renames, vec![(a, dir.path.join("c"))]);
+    }
+
+    #[test]
+    fn plan_rejects_invalid_names() {
+        let dir = TestDir::new("rename-invalid");
+        let a = dir.write("a", "");
+        let b = dir.write("b", "");
+        dir.write("taken", "");
+        let entries = [a, b];
+
+        for invalid in [
+            vec!["a"],
+            vec!["a", "b", "c"],
+            vec!["", "b"],
+            vec!["x/y", "b"],
+            vec!["..", "b"],
+            vec![".", "b"],
+            vec!["c", "c"],
+            vec!["taken", "b"],
+        ] {
+            assert!(
+                BulkRename::plan(&entries, &names(&invalid)).is_err(),
+                "{invalid:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn chains_and_cycles_are_applied_without_overwriting() {
+        let dir = TestDir::new("rename-cycle");
+        let a = dir.write("a", "a");
+        let b = dir.write("b", "b");
+        let c = dir.write("c", "c");
+
+        // a and b swap names, which needs temporary names, while c is renamed next to them
+        let renames =
+            BulkRename::plan(&[a.clone(), b.clone(), c.clone()], &names(&["b", "a", "d"])).unwrap();
+        let (changes, errors) = BulkRename::apply(&renames);
+
+        assert!(errors.is_empty());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "b");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.path.join("d")).unwrap(), "c");
+        assert!(c.symlink_metadata().is_err());
+        assert_eq!(fs::read_dir(&dir.path).unwrap().count(), 3);
+        assert!(!changes.is_empty());
+
+        let chain = BulkRename::plan(&[a.clone(), b.clone()], &names(&["b", "e"])).unwrap();
+        let (_, errors) = BulkRename::apply(&chain);
+
+        assert!(errors.is_empty());
+        assert_eq!(fs::read_to_string(&b).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dir.path.join("e")).unwrap(), "a");
+    }
+}
@@ -16,10 +16,10 @@
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use std::{
-    env::{self, current_dir, set_current_dir},
-    io::Result,
+    env::{self, current_dir},
+    io::{Error, ErrorKind, Result},
     os::unix::fs::{MetadataExt, PermissionsExt},
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::SystemTime,
 };
 use users::{get_group_by_gid, get_user_by_uid, Group, User};
@@ -27,6 +27,7 @@ use users::{get_group_by_gid, get_user_by_uid, Group, User};
 use crate::ui::popup::popup::show_error;
 
 /// Associates a `PathBuf` to their file name prepended by an icon
+#[derive(Clone)]
 pub struct Dir {
     pub pathbuf: PathBuf,
     pub display_name: String,
@@ -45,7 +46,7 @@ impl Dir {
     /// returns the entry to the parent directory +
     /// all of the entries in the current directory ordered directories first
     pub fn get_dir_entries_ordered(dir: PathBuf) -> Result<Vec<Dir>> {
-        //gets sorted list of files/directories in current working dir
+        //gets sorted list of files/directories in dir
         let entries: Vec<PathBuf> = dir
             .read_dir()?
             .map(|res| res.map(|e| e.path()))
@@ -67,12 +68,12 @@ impl Dir {
             .map(|r| (*r).clone())
             .collect();
 
-        let ordered_entries: Vec<PathBuf> = vec![folder_entries, file_entries].concat();
+        let ordered_entries: Vec<PathBuf> = [folder_entries, file_entries].concat();
 
         let mut res = <Vec<Dir>>::new();
 
         let parent = Dir {
-            pathbuf: current_dir()?.parent().unwrap_or(&dir).to_path_buf(),
+            pathbuf: dir.parent().unwrap_or(&dir).to_path_buf(),
             display_name: " ..".to_string(),
         };
 
@@ -86,7 +87,7 @@ impl Dir {
             res.push(d);
         }
 
-        return Ok(res);
+        Ok(res)
     }
 
     // TODO: add a crate for file icons
@@ -112,21 +113,14 @@ impl Dir {
             },
             Err(e) => {
                 show_error("Couldn't get current directory", e);
-                return Dir {
+                Dir {
                     pathbuf: PathBuf::default(),
                     display_name: Dir::get_display_name(PathBuf::default()),
-                };
+                }
             }
         }
     }
 
-    pub fn change_working_dir(path: PathBuf) {
-        match set_current_dir(path) {
-            Ok(_) => (),
-            Err(error) => show_error("Error opening directory/file", error),
-        }
-    }
-
     pub fn get_parent_dir(path: PathBuf) -> Dir {
         match path.parent() {
             Some(p) => Dir {
@@ -147,15 +141,62 @@ impl Dir {
     pub fn get_shortened_path(path: PathBuf) -> String {
         let home = env::var("HOME").unwrap_or_default();
 
-        return path.display().to_string().replace(&home.clone(), "~");
+        path.display().to_string().replace(&home.clone(), "~")
+    }
+
+    /// turns a path typed by the user into a real path, expanding a leading `~` to $HOME and
+    /// `$VAR`/`${VAR}` to the value of the environment variable
+    /// relative paths are taken from [base](Path)
+    pub fn expand_path(input: &str, base: &Path) -> Result<PathBuf> {
+        let mut expanded = String::new();
+        let mut chars = input.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                expanded.push(c);
+                continue;
+            }
+
+            let braced = chars.next_if_eq(&'{').is_some();
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+            if braced && chars.next_if_eq(&'}').is_none() {
+                return Err(Error::new(ErrorKind::InvalidInput, "missing '}'"));
+            }
+
+            // a lone $ is just part of the name
+            if name.is_empty() && !braced {
+                expanded.push('$');
+                continue;
+            }
+
+            match env::var(&name) {
+                Ok(value) => expanded.push_str(&value),
+                Err(_) => {
+                    return Err(Error::new(
+                        ErrorKind::NotFound,
+                        "environment variable ".to_string() + &name + " is not set",
+                    ))
+                }
+            }
+        }
+
+        let home = env::var("HOME").unwrap_or_default();
+        let expanded = match expanded.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => home + rest,
+            _ => expanded,
+        };
+
+        Ok(base.join(expanded))
     }
 
     pub fn get_entry_name(path: PathBuf) -> String {
-        return path
-            .file_name()
+        path.file_name()
             .unwrap_or_default()
             .to_string_lossy()
-            .to_string();
+            .to_string()
     }
 
     /// returns a string containing the file metadata
@@ -165,16 +206,15 @@ impl Dir {
             Ok(m) => {
                 let mut result = "".to_string();
 
-                result = result
-                    + if path.is_symlink() {
-                        "l"
-                    } else if path.is_dir() {
-                        "d"
-                    } else if path.is_file() {
-                        "-"
-                    } else {
-                        "?"
-                    };
+                result += if path.is_symlink() {
+                    "l"
+                } else if path.is_dir() {
+                    "d"
+                } else if path.is_file() {
+                    "-"
+                } else {
+                    "?"
+                };
 
                 // raw st_mode permissions
                 let mut st_mode = format!("{:o}", m.permissions().mode());
@@ -193,20 +233,20 @@ impl Dir {
                         .replace("6", "rw-")
                         .replace("7", "rwx"),
                 );
-                result.push_str(" ");
+                result.push(' ');
 
                 // number of hardlinks
                 let mut n_links = m.nlink().to_string();
                 if n_links.len() == 1 {
                     n_links = " ".to_string() + &n_links;
                 }
-                result.push_str(&(&n_links));
+                result.push_str(&n_links);
 
                 // owbership information
                 let owner_user = get_user_by_uid(m.uid());
                 let owner_group = get_group_by_gid(m.gid());
 
-                result.push_str(" ");
+                result.push(' ');
                 result.push_str(
                     &owner_user
                         .unwrap_or(User::new(000, "invalid", 0))
@@ -214,7 +254,7 @@ impl Dir {
                         .to_string_lossy(),
                 ); // extracts name or defaults to invalid
 
-                result.push_str(" ");
+                result.push(' ');
                 result.push_str(
                     &owner_group
                         .unwrap_or(Group::new(000, "invalid"))
@@ -222,13 +262,10 @@ impl Dir {
                         .to_string_lossy(),
                 ); // extracts name or defaults to invalid
 
-                result.push_str(" ");
+                result.push(' ');
 
-                let date_modified: DateTime<Utc> = m
-                    .modified()
-                    .or::<SystemTime>(Ok(SystemTime::UNIX_EPOCH))
-                    .unwrap()
-                    .into();
+                let date_modified: DateTime<Utc> =
+                    m.modified().unwrap_or(SystemTime::UNIX_EPOCH).into();
 
                 let date_string = date_modified.format("%d %b %Y %H:%S").to_string();
 
@@ -238,8 +275,75 @@ impl Dir {
             }
             Err(e) => {
                 show_error("Error reading file metadata", e);
-                return "error reading file permissions".to_string();
+                "error reading file permissions".to_string()
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths_start_at_the_base() {
+        let base = Path::new("/base");
+
+        assert_eq!(
+            Dir::expand_path("a/b", base).unwrap(),
+            Path::new("/base/a/b")
+        );
+        assert_eq!(Dir::expand_path("/a", base).unwrap(), Path::new("/a"));
+    }
+
+    #[test]
+    fn tilde_is_the_home_directory() {
+        let home = env::var("HOME").unwrap();
+        let base = Path::new("/base");
+
+        assert_eq!(Dir::expand_path("~", base).unwrap(), Path::new(&home));
+        assert_eq!(
+            Dir::expand_path("~/docs", base).unwrap(),
+            Path::new(&home).join("docs")
+        );
+        // only a whole leading component is the home directory
+        assert_eq!(
+            Dir::expand_path("~docs", base).unwrap(),
+            Path::new("/base/~docs")
+        );
+        assert_eq!(
+            Dir::expand_path("a/~", base).unwrap(),
+            Path::new("/base/a/~")
+        );
+    }
+
+    #[test]
+    fn environment_variables_are_expanded() {
+        let home = env::var("HOME").unwrap();
+        let base = Path::new("/base");
+
+        assert_eq!(
+            Dir::expand_path("$HOME/docs", base).unwrap(),
+            Path::new(&home).join("docs")
+        );
+        assert_eq!(
+            Dir::expand_path("${HOME}/docs", base).unwrap(),
+            Path::new(&home).join("docs")
+        );
+        assert_eq!(
+            Dir::expand_path("a$/b", base).unwrap(),
+            Path::new("/base/a$/b")
+        );
+
+        assert_eq!(
+            Dir::expand_path("${HOME", base).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            Dir::expand_path("$NAVFS_SURELY_NOT_SET", base)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,804 @@
+//! Recursive copy and move of directory entries
+//!
+//! Keeps the actual file system work out of the `FilePicker` so the widget only has to decide
+//! *what* to paste and *where*, and report what went wrong.
+//!
+//! Pasting happens in two steps: first a [PastePlan] is built, asking the caller how to resolve
+//! every name conflict found in the destination, then each step of the plan is executed.
+//!
+//! Executing reports into a [Progress] so it can run in the background, and stops as soon as it
+//! notices the [Progress] was cancelled.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let plan = FileOps::plan_paste(buffer, dest_dir, PasteMode::Copy, &mut |_src, _dest| {
+//!     Some(ConflictResolution::Skip)
+//! });
+//!
+//! if let Some(plan) = plan {
+//!     let progress = Progress::default();
+//!     for step in plan.steps {
+//!         let _overwritten = FileOps::execute_step(&step.action, PasteMode::Copy, &progress)?;
+//!     }
+//! }
+//! ```
+
+use std::{
+    collections::HashSet,
+    fs::{self, File, FileTimes, Metadata},
+    io::{Error, ErrorKind, Read, Result, Write},
+    os::unix::fs::{symlink, MetadataExt},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+use super::trash::Trash;
+
+/// size of the chunks files are copied in, between chunks it checks for cancellation
+const COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// how an entry gets pasted into its destination
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum PasteMode {
+    Copy,
+    Move,
+}
+
+/// what to do with an entry whose name is already taken in the destination
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum ConflictResolution {
+    Overwrite,
+    Skip,
+    Rename,
+}
+
+/// a single action of a paste, with its name conflicts already resolved
+pub enum PasteAction {
+    /// copies/moves [src] to [dest], deleting what is at [dest] first if [replace] is set
+    Paste {
+        src: PathBuf,
+        dest: PathBuf,
+        replace: bool,
+    },
+    /// removes a source directory that was merged into an existing one, if nothing was left in it
+    RemoveMergedDir(PathBuf),
+}
+
+/// associates a [PasteAction] to the entry in the buffer that originated it
+pub struct PasteStep {
+    pub origin: PathBuf,
+    pub action: PasteAction,
+}
+
+/// everything that has to be done to paste a list of entries
+///
+/// [steps](Vec<PasteStep>): actions to be executed, in order
+/// [errors](Vec<(PathBuf, Error)>): entries that can't be pasted at all
+/// [reserved](HashSet<PathBuf>): destinations already taken by a step, even if they don't exist yet
+#[derive(Default)]
+pub struct PastePlan {
+    pub steps: Vec<PasteStep>,
+    pub errors: Vec<(PathBuf, Error)>,
+    reserved: HashSet<PathBuf>,
+}
+
+/// counters updated while executing file operations, shared with whoever is displaying them
+#[derive(Default)]
+pub struct Progress {
+    pub entries_total: AtomicU64,
+    pub entries_done: AtomicU64,
+    pub bytes_total: AtomicU64,
+    pub bytes_done: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+pub struct FileOps {}
+
+impl FileOps {
+    /// builds the plan for pasting [entries](Vec<PathBuf>) into [dest_dir](PathBuf)
+    ///
+    /// directories that already exist in the destination are merged, calling [resolve] for each
+    /// conflicting child instead. [resolve] receives the source and the taken destination and
+    /// returns `None` to cancel the whole paste, in which case this also returns `None`
+    pub fn plan_paste(
+        entries: Vec<PathBuf>,
+        dest_dir: PathBuf,
+        mode: PasteMode,
+        resolve: &mut dyn FnMut(&Path, &Path) -> Option<ConflictResolution>,
+    ) -> Option<PastePlan> {
+        let mut plan = PastePlan::default();
+
+        for entry in entries {
+            FileOps::plan_entry(&entry, &entry, &dest_dir, mode, resolve, &mut plan)?;
+        }
+
+        Some(plan)
+    }
+
+    /// adds the actions needed to paste [src](Path) inside [dest_dir](Path) to the [plan](PastePlan)
+    fn plan_entry(
+        origin: &Path,
+        src: &Path,
+        dest_dir: &Path,
+        mode: PasteMode,
+        resolve: &mut dyn FnMut(&Path, &Path) -> Option<ConflictResolution>,
+        plan: &mut PastePlan,
+    ) -> Option<()> {
+        // poor man try catch
+        let checked = (|| -> Result<(PathBuf, Metadata)> {
+            Ok((
+                FileOps::get_destination(src, dest_dir)?,
+                src.symlink_metadata()?,
+            ))
+        })();
+
+        let (dest, src_metadata) = match checked {
+            Ok(value) => value,
+            Err(error) => {
+                plan.errors.push((origin.to_path_buf(), error));
+                return Some(());
+            }
+        };
+
+        // another entry of this paste already goes there, like one with the same name from another
+        // directory. it doesn't exist yet, so it can only be skipped or renamed
+        if plan.reserved.contains(&dest) {
+            match resolve(src, &dest)? {
+                ConflictResolution::Overwrite => plan.errors.push((
+                    origin.to_path_buf(),
+                    Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("another entry is already pasted as {}", dest.display()),
+                    ),
+                )),
+                ConflictResolution::Rename => {
+                    let renamed = FileOps::get_free_name(&dest, &plan.reserved);
+                    plan.push(origin, src, renamed, false);
+                }
+                ConflictResolution::Skip => (),
+            }
+            return Some(());
+        }
+
+        let dest_metadata = match dest.symlink_metadata() {
+            Ok(metadata) => metadata,
+            // nothing in the way
+            Err(_) => {
+                plan.push(origin, src, dest, false);
+                return Some(());
+            }
+        };
+
+        let is_same_entry =
+            src_metadata.dev() == dest_metadata.dev() && src_metadata.ino() == dest_metadata.ino();
+
+        if src_metadata.is_dir() && dest_metadata.is_dir() && !is_same_entry {
+            // merges both directories, only the conflicting children need to be resolved
+            match fs::read_dir(src).and_then(|entries| entries.collect::<Result<Vec<_>>>()) {
+                Ok(children) => {
+                    for child in children {
+                        FileOps::plan_entry(origin, &child.path(), &dest, mode, resolve, plan)?;
+                    }
+                    if mode == PasteMode::Move {
+                        plan.steps.push(PasteStep {
+                            origin: origin.to_path_buf(),
+                            action: PasteAction::RemoveMergedDir(src.to_path_buf()),
+                        });
+                    }
+                }
+                Err(error) => plan.errors.push((origin.to_path_buf(), error)),
+            }
+            return Some(());
+        }
+
+        match resolve(src, &dest)? {
+            ConflictResolution::Overwrite => {
+                // replacing the destination would delete the source along with it
+                if is_same_entry || src.starts_with(&dest) {
+                    plan.errors.push((
+                        origin.to_path_buf(),
+                        Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("can't overwrite {} with itself", dest.display()),
+                        ),
+                    ));
+                } else {
+                    plan.push(origin, src, dest, true);
+                }
+            }
+            ConflictResolution::Rename => {
+                let renamed = FileOps::get_free_name(&dest, &plan.reserved);
+                plan.push(origin, src, renamed, false);
+            }
+            ConflictResolution::Skip => (),
+        }
+
+        Some(())
+    }
+
+    /// executes a single action from a [PastePlan]
+    /// a copy that fails or gets cancelled midway removes what it created instead of leaving it
+    /// half done
+    ///
+    /// entries being overwritten are moved to the trash, so they can still be recovered. returns
+    /// where in the trash the overwritten entry is
+    pub fn execute_step(
+        action: &PasteAction,
+        mode: PasteMode,
+        progress: &Progress,
+    ) -> Result<Option<PathBuf>> {
+        match action {
+            PasteAction::Paste { src, dest, replace } => {
+                // something may have taken the name since the paste was planned, and renaming
+                // over it would silently replace it
+                if !replace && dest.symlink_metadata().is_ok() {
+                    return Err(Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("{} already exists", dest.display()),
+                    ));
+                }
+
+                let replaced = match replace {
+                    true => Some(Trash::trash_entry(dest.clone())?),
+                    false => None,
+                };
+
+                let res = match mode {
+                    PasteMode::Copy => FileOps::copy_recursive(src, dest, progress),
+                    PasteMode::Move => FileOps::move_recursive(src, dest, progress),
+                };
+
+                if let Err(error) = res {
+                    // puts back what was overwritten, since nothing replaced it
+                    if let Some(trashed) = replaced {
+                        let _ = Trash::get_entry(&trashed)
+                            .and_then(|entry| Trash::restore_entry(&entry, dest));
+                    }
+                    return Err(error);
+                }
+
+                Ok(replaced)
+            }
+            PasteAction::RemoveMergedDir(dir) => match fs::remove_dir(dir) {
+                // some children were skipped, so the directory has to stay
+                Err(error) if error.kind() == ErrorKind::DirectoryNotEmpty => Ok(None),
+                Err(error) => Err(error),
+                Ok(_) => Ok(None),
+            },
+        }
+    }
+
+    /// moves [src](Path) to [dest](Path)
+    ///
+    /// it's a simple rename when both are on the same file system, otherwise the entry is copied
+    /// and the original deleted only after the copy finished successfully. fifos, sockets and
+    /// devices can't be copied, so they can only be moved within the same file system
+    pub fn move_recursive(src: &Path, dest: &Path, progress: &Progress) -> Result<()> {
+        match fs::rename(src, dest) {
+            Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+                // a failed copy doesn't leave anything behind, the source is still intact
+                FileOps::copy_recursive(src, dest, progress)?;
+                FileOps::remove_entry(src)
+            }
+            res => res,
+        }
+    }
+
+    /// permanently deletes a file, symlink or whole directory tree, one entry at a time so it can
+    /// report progress and be cancelled
+    pub fn delete_recursive(path: &Path, progress: &Progress) -> Result<()> {
+        progress.check_cancelled()?;
+
+        let metadata = path.symlink_metadata()?;
+
+        if metadata.is_dir() {
+            for entry in fs::read_dir(path)? {
+                FileOps::delete_recursive(&entry?.path(), progress)?;
+            }
+            fs::remove_dir(path)?;
+        } else {
+            fs::remove_file(path)?;
+            progress.add_bytes(metadata.len());
+        }
+
+        progress.entries_done.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// returns how many entries and bytes there are in [path](Path), counting itself and, for
+    /// directories, everything inside it. symlinks are not followed
+    pub fn measure(path: &Path) -> (u64, u64) {
+        match path.symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| FileOps::measure(&entry.path()))
+                        .fold((1, 0), |(entries, bytes), (e, b)| (entries + e, bytes + b))
+                })
+                .unwrap_or((1, 0)),
+            Ok(metadata) => (1, metadata.len()),
+            Err(_) => (0, 0),
+        }
+    }
+
+    /// creates an empty file or directory at [path](Path), along with the directories it's in
+    /// returns the first of those that didn't exist before, to know what to remove to undo it
+    pub fn create_entry(path: &Path, is_dir: bool) -> Result<PathBuf> {
+        let top = path
+            .ancestors()
+            .take_while(|ancestor| ancestor.symlink_metadata().is_err())
+            .last()
+            .ok_or(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            ))?
+            .to_path_buf();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match is_dir {
+            true => fs::create_dir(path)?,
+            false => {
+                File::create_new(path)?;
+            }
+        }
+
+        Ok(top)
+    }
+
+    /// permanently deletes a file, symlink or whole directory tree
+    pub fn remove_entry(path: &Path) -> Result<()> {
+        if path.symlink_metadata()?.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+    }
+
+    /// returns where [src](Path) would end up if pasted inside [dest_dir](Path)
+    /// errors if that would mean pasting a directory into itself
+    fn get_destination(src: &Path, dest_dir: &Path) -> Result<PathBuf> {
+        let name = src.file_name().ok_or(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} has no file name", src.display()),
+        ))?;
+
+        // compares canonical paths so `..` and symlinks can't sneak a directory inside itself
+        if src.symlink_metadata()?.is_dir()
+            && dest_dir.canonicalize()?.starts_with(src.canonicalize()?)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("can't paste {} into itself", src.display()),
+            ));
+        }
+
+        Ok(dest_dir.join(name))
+    }
+
+    /// returns the first path not taken in the form `name_1.ext`, `name_2.ext`...
+    /// paths in [reserved](HashSet<PathBuf>) count as taken even if they don't exist yet
+    ///
+    /// the extension starts at the first dot, so `archive.tar.gz` becomes `archive_1.tar.gz`. a
+    /// leading dot is part of the name, so `.bashrc` becomes `.bashrc_1`
+    pub fn get_free_name(path: &Path, reserved: &HashSet<PathBuf>) -> PathBuf {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let (stem, extension) = match name.char_indices().skip(1).find(|(_, c)| *c == '.') {
+            Some((dot, _)) => name.split_at(dot),
+            None => (name.as_ref(), ""),
+        };
+
+        (1..)
+            .map(|n| path.with_file_name(format!("{stem}_{n}{extension}")))
+            .find(|candidate| {
+                candidate.symlink_metadata().is_err() && !reserved.contains(candidate)
+            })
+            .unwrap_or(path.to_path_buf())
+    }
+
+    /// copies a single entry, recursing into directories
+    /// symlinks are recreated as symlinks instead of being followed. fifos, sockets and devices
+    /// are not copied, the copy fails on them
+    ///
+    /// if the copy fails midway whatever it created is removed. it never touches an entry that
+    /// was already at [dest](Path), creating it fails instead
+    pub fn copy_recursive(src: &Path, dest: &Path, progress: &Progress) -> Result<()> {
+        progress.check_cancelled()?;
+
+        let metadata = src.symlink_metadata()?;
+
+        if metadata.is_symlink() {
+            symlink(fs::read_link(src)?, dest)?;
+            progress.entries_done.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        // fifos block when opened and devices like /dev/zero never end, only regular files have
+        // contents that can be copied
+        if !metadata.is_dir() && !metadata.is_file() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!("{} is not a regular file", src.display()),
+            ));
+        }
+
+        // from here on dest was created by this copy, so it's safe to remove if something fails
+        let res = if metadata.is_dir() {
+            fs::create_dir(dest)?;
+            // poor man try catch
+            (|| -> Result<()> {
+                for entry in fs::read_dir(src)? {
+                    let entry = entry?;
+                    let child_dest = dest.join(entry.file_name());
+                    FileOps::copy_recursive(&entry.path(), &child_dest, progress)?;
+                }
+                Ok(())
+            })()
+        } else {
+            let writer = File::create_new(dest)?;
+            FileOps::copy_file_contents(src, writer, progress)
+        };
+
+        // permissions are applied only after the children are written, otherwise copying a
+        // read only directory would fail on its first child
+        let res = res.and_then(|_| {
+            fs::set_permissions(dest, metadata.permissions())?;
+            FileOps::copy_times(&metadata, dest)
+        });
+
+        if let Err(error) = res {
+            let _ = FileOps::remove_entry(dest);
+            return Err(error);
+        }
+
+        progress.entries_done.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// copies the contents of a regular file into [writer](File) in chunks, reporting every chunk
+    /// written
+    fn copy_file_contents(src: &Path, mut writer: File, progress: &Progress) -> Result<()> {
+        let mut reader = File::open(src)?;
+        let mut chunk = vec![0; COPY_CHUNK_SIZE];
+
+        loop {
+            progress.check_cancelled()?;
+
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => return Ok(()),
+                Ok(read) => read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+
+            writer.write_all(&chunk[..read])?;
+            progress.add_bytes(read as u64);
+        }
+    }
+
+    /// sets the access and modification times of [dest](Path) to the ones in [metadata](Metadata)
+    fn copy_times(metadata: &Metadata, dest: &Path) -> Result<()> {
+        let times = FileTimes::new()
+            .set_accessed(metadata.accessed()?)
+            .set_modified(metadata.modified()?);
+
+        // opening read only is enough for futimens, and also works on directories
+        File::open(dest)?.set_times(times)
+    }
+}
+
+impl PastePlan {
+    fn push(&mut self, origin: &Path, src: &Path, dest: PathBuf, replace: bool) {
+        self.reserved.insert(dest.clone());
+        self.steps.push(PasteStep {
+            origin: origin.to_path_buf(),
+            action: PasteAction::Paste {
+                src: src.to_path_buf(),
+                dest,
+                replace,
+            },
+        });
+    }
+}
+
+impl Progress {
+    /// asks the operation using this to stop as soon as possible
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// errors with [ErrorKind::Interrupted] if the operation was cancelled
+    fn check_cancelled(&self) -> Result<()> {
+        match self.is_cancelled() {
+            true => Err(Error::new(ErrorKind::Interrupted, "cancelled")),
+            false => Ok(()),
+        }
+    }
+
+    fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// adds [sizes](&[(u64, u64)]) as returned by [FileOps::measure] to the totals
+    pub fn add_totals(&self, sizes: &[(u64, u64)]) {
+        for (entries, bytes) in sizes {
+            self.entries_total.fetch_add(*entries, Ordering::Relaxed);
+            self.bytes_total.fetch_add(*bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// overwrites the counters with the exact values once a step is known to be finished
+    pub fn set_done(&self, (entries, bytes): (u64, u64)) {
+        self.entries_done.store(entries, Ordering::Relaxed);
+        self.bytes_done.store(bytes, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::net::UnixListener, process::Command};
+
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    /// where each paste of [plan](PastePlan) goes, and if it replaces what's there
+    fn get_dests(plan: &PastePlan) -> Vec<(PathBuf, bool)> {
+        plan.steps
+            .iter()
+            .filter_map(|step| match &step.action {
+                PasteAction::Paste { dest, replace, .. } => Some((dest.clone(), *replace)),
+                PasteAction::RemoveMergedDir(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn free_name_keeps_the_whole_extension() {
+        let dir = TestDir::new("free-name");
+        let none = HashSet::new();
+
+        assert_eq!(
+            FileOps::get_free_name(&dir.path.join("archive.tar.gz"), &none),
+            dir.path.join("archive_1.tar.gz")
+        );
+        assert_eq!(
+            FileOps::get_free_name(&dir.path.join(".bashrc"), &none),
+            dir.path.join(".bashrc_1")
+        );
+        assert_eq!(
+            FileOps::get_free_name(&dir.path.join("notes"), &none),
+            dir.path.join("notes_1")
+        );
+    }
+
+    #[test]
+    fn free_name_skips_taken_and_reserved_names() {
+        let dir = TestDir::new("free-name-taken");
+        dir.write("a_1.txt", "");
+        let reserved = HashSet::from([dir.path.join("a_2.txt")]);
+
+        assert_eq!(
+            FileOps::get_free_name(&dir.path.join("a.txt"), &reserved),
+            dir.path.join("a_3.txt")
+        );
+    }
+
+    #[test]
+    fn plan_renames_entries_with_the_same_name() {
+        let dir = TestDir::new("plan-same-name");
+        let first = dir.write("one/x.txt", "1");
+        let second = dir.write("two/x.txt", "2");
+        let dest_dir = dir.path.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+
+        let mut conflicts = 0;
+        let plan = FileOps::plan_paste(
+            vec![first, second],
+            dest_dir.clone(),
+            PasteMode::Copy,
+            &mut |_, _| {
+                conflicts += 1;
+                Some(ConflictResolution::Rename)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            get_dests(&plan),
+            vec![
+                (dest_dir.join("x.txt"), false),
+                (dest_dir.join("x_1.txt"), false)
+            ]
+        );
+    }
+
+    #[test]
+    fn plan_never_overwrites_another_entry_of_the_paste() {
+        let dir = TestDir::new("plan-same-name-overwrite");
+        let first = dir.write("one/x.txt", "1");
+        let second = dir.write("two/x.txt", "2");
+        let dest_dir = dir.path.join("dest");
+        fs::create_dir(&dest_dir).unwrap();
+
+        let plan = FileOps::plan_paste(
+            vec![first, second.clone()],
+            dest_dir.clone(),
+            PasteMode::Copy,
+            &mut |_, _| Some(ConflictResolution::Overwrite),
+        )
+        .unwrap();
+
+        assert_eq!(get_dests(&plan), vec![(dest_dir.join("x.txt"), false)]);
+        assert_eq!(plan.errors.len(), 1);
+        assert_eq!(plan.errors[0].0, second);
+        assert_eq!(plan.errors[0].1.kind(), ErrorKind::AlreadyExists);
+    }
+
+    #[test]
+    fn plan_resolves_existing_entries() {
+        let dir = TestDir::new("plan-existing");
+        let src = dir.write("src/x.txt", "new");
+        let dest_dir = dir.path.join("dest");
+        dir.write("dest/x.txt", "old");
+
+        let plan_with = |resolution| {
+            FileOps::plan_paste(
+                vec![src.clone()],
+                dest_dir.clone(),
+                PasteMode::Copy,
+                &mut |_, _| Some(resolution),
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            get_dests(&plan_with(ConflictResolution::Overwrite)),
+            vec![(dest_dir.join("x.txt"), true)]
+        );
+        assert_eq!(
+            get_dests(&plan_with(ConflictResolution::Rename)),
+            vec![(dest_dir.join("x_1.txt"), false)]
+        );
+        assert!(plan_with(ConflictResolution::Skip).steps.is_empty());
+
+        let cancelled = FileOps::plan_paste(
+            vec![src.clone()],
+            dest_dir.clone(),
+            PasteMode::Copy,
+            &mut |_, _| None,
+        );
+        assert!(cancelled.is_none());
+    }
+
+    #[test]
+    fn plan_merges_existing_directories() {
+        let dir = TestDir::new("plan-merge");
+        dir.write("src/docs/a.txt", "");
+        dir.write("src/docs/b.txt", "");
+        dir.write("dest/docs/a.txt", "");
+        let dest_dir = dir.path.join("dest");
+
+        let plan = FileOps::plan_paste(
+            vec![dir.path.join("src/docs")],
+            dest_dir.clone(),
+            PasteMode::Move,
+            &mut |_, _| Some(ConflictResolution::Skip),
+        )
+        .unwrap();
+
+        assert_eq!(get_dests(&plan), vec![(dest_dir.join("docs/b.txt"), false)]);
+        assert!(matches!(
+            plan.steps.last().map(|step| &step.action),
+            Some(PasteAction::RemoveMergedDir(merged)) if *merged == dir.path.join("src/docs")
+        ));
+    }
+
+    #[test]
+    fn step_fails_if_the_destination_was_taken_since_planning() {
+        let dir = TestDir::new("step-taken");
+        let src = dir.write("src.txt", "new");
+        let dest = dir.write("dest.txt", "old");
+
+        let action = PasteAction::Paste {
+            src: src.clone(),
+            dest: dest.clone(),
+            replace: false,
+        };
+        for mode in [PasteMode::Copy, PasteMode::Move] {
+            let error = FileOps::execute_step(&action, mode, &Progress::default()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        }
+
+        assert_eq!(fs::read_to_string(&src).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "old");
+    }
+
+    #[test]
+    fn copy_never_touches_an_existing_destination() {
+        let dir = TestDir::new("copy-existing");
+        let src = dir.write("src.txt", "new");
+        let dest = dir.write("dest.txt", "old");
+
+        let error = FileOps::copy_recursive(&src, &dest, &Progress::default()).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "old");
+    }
+
+    #[test]
+    fn failed_copy_removes_what_it_created() {
+        let dir = TestDir::new("copy-failed");
+        dir.write("src/a.txt", "a");
+        // sockets aren't copied, so the copy fails on it after creating the directory
+        let _socket = UnixListener::bind(dir.path.join("src/socket")).unwrap();
+        let src = dir.path.join("src");
+        let dest = dir.path.join("dest");
+
+        assert!(FileOps::copy_recursive(&src, &dest, &Progress::default()).is_err());
+
+        assert!(dest.symlink_metadata().is_err());
+        assert_eq!(fs::read_to_string(src.join("a.txt")).unwrap(), "a");
+    }
+
+    #[test]
+    fn cancelled_copy_removes_what_it_created() {
+        let dir = TestDir::new("copy-cancelled");
+        let src = dir.write("src.txt", "contents");
+        let dest = dir.path.join("dest.txt");
+        let progress = Progress::default();
+        progress.cancel();
+
+        let action = PasteAction::Paste {
+            src,
+            dest: dest.clone(),
+            replace: false,
+        };
+        assert!(FileOps::execute_step(&action, PasteMode::Copy, &progress).is_err());
+        assert!(dest.symlink_metadata().is_err());
+    }
+
+    #[test]
+    fn copy_recreates_directories_and_symlinks() {
+        let dir = TestDir::new("copy-tree");
+        dir.write("src/sub/a.txt", "a");
+        symlink("sub/a.txt", dir.path.join("src/link")).unwrap();
+        let dest = dir.path.join("dest");
+
+        FileOps::copy_recursive(&dir.path.join("src"), &dest, &Progress::default()).unwrap();
+
+        assert_eq!(fs::read_to_string(dest.join("sub/a.txt")).unwrap(), "a");
+        assert_eq!(
+            fs::read_link(dest.join("link")).unwrap(),
+            PathBuf::from("sub/a.txt")
+        );
+    }
+
+    #[test]
+    fn special_files_are_not_copied() {
+        let dir = TestDir::new("copy-special");
+        let fifo = dir.path.join("fifo");
+        assert!(Command::new("mkfifo")
+            .arg(&fifo)
+            .status()
+            .unwrap()
+            .success());
+
+        // opening the fifo or reading the device would never return
+        for src in [fifo.as_path(), Path::new("/dev/zero")] {
+            let dest = dir.path.join("dest");
+            let error = FileOps::copy_recursive(src, &dest, &Progress::default()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::Unsupported);
+            assert!(dest.symlink_metadata().is_err());
+        }
+    }
+}
@@ -1,57 +1,117 @@
 use crate::{
     theme::Theme,
-    ui::popup::{
-        self,
-        popup::{show_confirmation, show_error, show_info},
+    ui::{
+        bookmarks_view::bookmarks_view::BookmarksView,
+        dir_jump::dir_jump::DirJump,
+        fuzzy_finder::fuzzy_finder::FuzzyFinder,
+        grep_view::grep_view::GrepView,
+        popup::{
+            self,
+            popup::{
+                show_confirmation, show_conflict, show_info, show_input, show_input_with_completion,
+            },
+        },
+        text_input::text_input::TextInput,
+        trash_view::trash_view::TrashView,
     },
 };
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use itertools::Itertools;
 use ratatui::{prelude::*, widgets::*};
+use regex::RegexBuilder;
 use std::{
-    fs::{remove_dir_all, remove_file},
+    fs,
     io::Result,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
-use super::dir::Dir;
+use super::{
+    bookmarks::Bookmarks,
+    bulk_rename::BulkRename,
+    dir::Dir,
+    file_ops::{ConflictResolution, FileOps, PasteMode},
+    frecency::Frecency,
+    history::{History, Visit},
+    jobs::{JobKind, JobReport, Jobs},
+    journal::Change,
+};
+
+/// which entry the cursor is put on when the entries are read again
+pub enum Selection {
+    /// the entry at the index, or the last one if there are fewer entries now
+    Index(usize),
+    /// the entry with this path, or the first one if it's not in the directory
+    Entry(PathBuf),
+}
 
 /// Wrapper widget around the [List](ratatui::widgets::List) to manage its events and state
 ///
 /// [items](Vec<Dir>): the current entries rendered in the widget
+/// [curr_dir](PathBuf): the directory whose entries are being rendered
 /// [index](usize): the index of the entry under the cursor
 /// [buffer](Vec<PathBuf): buffered (selected) items currently
+/// [jobs](Jobs): queue where long file system operations are sent to run in the background
+/// [filter](String): only entries whose name contains it are shown, empty shows every entry
+/// [filter_input](Option<TextInput>): the search box, while the user is typing the filter
+/// [history](History): directories visited, to go back and forward through them
 /// [active](bool): if the widget is currently selected
 /// [needs_redraw](bool): tells the parent widget it needs to redraw itself
 pub struct FilePicker {
     items: Vec<Dir>,
+    curr_dir: PathBuf,
     index: usize,
     buffer: Vec<PathBuf>,
+    jobs: Jobs,
+    filter: String,
+    filter_input: Option<TextInput>,
+    history: History,
     pub active: bool,
     pub needs_redraw: bool,
 }
 
 impl FilePicker {
-    pub fn new(is_selected: bool) -> FilePicker {
+    pub fn new(is_selected: bool, jobs: Jobs) -> FilePicker {
         FilePicker {
             items: <Vec<Dir>>::new(),
+            curr_dir: PathBuf::new(),
             index: 0,
             buffer: <Vec<PathBuf>>::new(),
+            jobs,
+            filter: String::new(),
+            filter_input: None,
+            history: History::default(),
             active: is_selected,
             needs_redraw: false,
         }
     }
 
     /// renders the widget with [dir](Option<PathBuf>) as the working directory
-    /// and the entry at the [index](Option<usize>) selected
+    /// and the entry chosen by [selection](Option<Selection>) selected
+    ///
+    /// on [dir](Option<PathBuf>) == `None` reads the directory being displayed again
+    /// on [selection](Option<Selection>) == `None` defaults to the first entry on the list (if the
+    /// directory is not empty, the first after the parent entry)
     ///
-    /// on [dir](Option<PathBuf>) == `None` defaults current working directory
-    /// on [index](Option<usize>) == `None` defaults to the first entry on the list (if the directory
-    /// is not empty, the first after the parent entry)
-    pub fn initialize(&mut self, dir: Option<PathBuf>, index: Option<usize>) {
+    /// returns `false` if the directory couldn't be read, an entry with the error is shown instead
+    pub fn initialize(&mut self, dir: Option<PathBuf>, selection: Option<Selection>) -> bool {
+        let dir = dir.unwrap_or(self.curr_dir.clone());
+        let entries = Dir::get_dir_entries_ordered(dir.clone());
+        self.initialize_with_entries(dir, entries, selection)
+    }
+
+    /// like [FilePicker::initialize], with [entries](Result<Vec<Dir>>) being what
+    /// [Dir::get_dir_entries_ordered] returned for [dir](PathBuf), for when they were read already
+    pub fn initialize_with_entries(
+        &mut self,
+        dir: PathBuf,
+        entries: Result<Vec<Dir>>,
+        selection: Option<Selection>,
+    ) -> bool {
+        self.curr_dir = dir;
+
         // poor man try catch
         if let Err(error) = (|| -> Result<()> {
-            let mut items =
-                Dir::get_dir_entries_ordered(dir.unwrap_or(Dir::get_cur_dir().pathbuf))?;
+            let mut items = entries?;
 
             // displays which entries are in the buffer to the user
             for item in items.iter_mut() {
@@ -68,43 +128,69 @@ impl FilePicker {
                 }
             }
 
+            // the parent entry is always kept, so it's still possible to go up while filtering
+            if !self.filter.is_empty() {
+                items = items
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, item)| {
+                        *index == 0
+                            || FilePicker::find_match(
+                                &Dir::get_entry_name(item.pathbuf.clone()),
+                                &self.filter,
+                            )
+                            .is_some()
+                    })
+                    .map(|(_, item)| item)
+                    .collect();
+            }
+
             self.items = items;
 
             // chooses where the cursor should be
             // if an index was passed as argument it puts the cursor on that index if it exists, if
-            // not, or if no index passed, does this:
+            // an entry was passed it puts the cursor on it if it's in the directory, if not, or if
+            // nothing was passed, does this:
             // if the directory has entries the cursor is put on the first entry
             // if the directory is empty the cursor is put on the parent folder entry
-            match index {
-                Some(value) => {
-                    // important to not index a negative value
-                    self.index = if self.items.len() as i32 > value as i32 - 1 {
-                        value
-                    } else {
-                        // checks if the directory is not empty
-                        // (in which case the only entry will be the parent)
-                        if self.items.len() as i32 > 1 {
-                            1
-                        } else {
-                            0
-                        }
-                    }
+            let first = if self.items.len() as i32 > 1 { 1 } else { 0 };
+            match selection {
+                // if the index is past the end (entries were deleted) it selects the last entry
+                Some(Selection::Index(value)) => self.index = value.min(self.items.len() - 1),
+                Some(Selection::Entry(path)) => {
+                    self.index = self
+                        .items
+                        .iter()
+                        .skip(1) // the parent entry is not in the directory
+                        .position(|item| item.pathbuf == path)
+                        .map(|position| position + 1)
+                        .unwrap_or(first)
                 }
-                // in case no index is passed it selects either the parent, if the directory is
+                // in case nothing is passed it selects either the parent, if the directory is
                 // empty, or the first entry after parent, if not.
-                None => self.index = if self.items.len() as i32 > 1 { 1 } else { 0 },
+                None => self.index = first,
             }
 
             Ok(())
         })() {
             self.items = vec![Dir {
-                pathbuf: Dir::get_cur_dir().pathbuf,
+                pathbuf: self.curr_dir.clone(),
                 display_name: "Couldn't read entry: ".to_string() + &error.to_string(),
             }];
+            // the cursor may have been further down the list being replaced
+            self.index = 0;
+            return false;
         }
+        true
     }
 
     pub fn handle_keys(&mut self, key: KeyEvent) {
+        // while the search box is open every key goes to it
+        if self.filter_input.is_some() {
+            self.handle_filter_keys(key);
+            return;
+        }
+
         // it's important to check that the event is a key press event as
         // crossterm also emits key release and repeat events on Windows.
         match key.code {
@@ -112,8 +198,14 @@ impl FilePicker {
             KeyCode::Char('j') => self.select_next(), // moves the cursor down in the list
             KeyCode::Char('k') => self.select_prev(), // moves the cursor up in the list
             KeyCode::Char('l') => self.open_selected_dir(), // opens the entry under cursor
+            KeyCode::Char('H') => self.go_back(), // goes back to the previous directory visited
+            KeyCode::Char('L') => self.go_forward(), // goes forward to the directory left with [H]
             KeyCode::Char(' ') => self.buffer_item(), // adds/removes item under cursor from buffer
-            KeyCode::Char('d') => self.delete_sel_entry(), // deletes entry under cursor
+            KeyCode::Char('d') => self.trash_sel_entry(), // moves entry under cursor to the trash
+            KeyCode::Char('D') => self.delete_sel_entry(), // permanently deletes entry under cursor
+            KeyCode::Char('r') => self.rename_sel_entry(), // renames entry under cursor
+            KeyCode::Char('n') => self.create_entry(false), // creates a new file
+            KeyCode::Char('N') => self.create_entry(true), // creates a new directory
             KeyCode::Char('g') => self.select_first(), // selects first entry after parent entry
             KeyCode::Char('G') => self.select_last(), // selects last entry on the list
             KeyCode::Char('b') => {
@@ -130,14 +222,22 @@ impl FilePicker {
                                 KeyCode::Char('c') => {
                                     self.buffer.clear();
                                     let curr_displaying_dir = self.get_curr_displaying_dir();
-                                    self.initialize(Some(curr_displaying_dir), Some(self.index))
-                                }
-                                // [bd] deletes all files in buffer
-                                KeyCode::Char('d') => {
-                                    if self.delete_files(self.buffer.clone()) {
-                                        self.buffer.clear();
-                                    }
+                                    self.initialize(
+                                        Some(curr_displaying_dir),
+                                        Some(Selection::Index(self.index)),
+                                    );
                                 }
+                                // [bp] pastes a copy of all files in buffer
+                                KeyCode::Char('p') => self.paste_buffer(PasteMode::Copy),
+                                // [bm] moves all files in buffer here
+                                KeyCode::Char('m') => self.paste_buffer(PasteMode::Move),
+                                // [bd] moves all files in buffer to the trash
+                                // entries are removed from the buffer once the job finishes
+                                KeyCode::Char('d') => self.trash_files(self.buffer.clone()),
+                                // [bD] permanently deletes all files in buffer
+                                KeyCode::Char('D') => self.delete_files(self.buffer.clone()),
+                                // [br] renames all files in buffer in $EDITOR
+                                KeyCode::Char('r') => self.bulk_rename_buffer(),
                                 _ => (),
                             }
                         }
@@ -145,14 +245,317 @@ impl FilePicker {
                     }
                 }
             }
-            KeyCode::Char('?') => self.show_help(), // shows keybindings popup
+            KeyCode::Char('m') => {
+                // [m<key>] bookmarks the directory being displayed under the next key typed
+                if let Some(key) = FilePicker::read_bookmark_key() {
+                    self.set_bookmark(key)
+                }
+            }
+            KeyCode::Char('\'') => {
+                // ['<key>] jumps to the directory bookmarked under the next key typed
+                if let Some(key) = FilePicker::read_bookmark_key() {
+                    self.jump_to_bookmark(key)
+                }
+            }
+            KeyCode::Char('B') => self.show_bookmarks(), // lists the bookmarks
+            KeyCode::Char('T') => self.show_trash(),     // shows trashed entries
+            KeyCode::Char('u') => self.jobs.undo(),      // reverts the last file operation
+            KeyCode::Char('f') => self.find_entry(),     // fuzzy finds an entry in the subtree
+            KeyCode::Char('z') => self.jump_to_visited(), // jumps to a frecent directory
+            KeyCode::Char(':') => self.jump_to_path(),   // opens a directory typed by the user
+            KeyCode::Char('s') => self.search_in_files(false), // searches text in the files
+            KeyCode::Char('S') => self.search_in_files(true), // searches a regex in the files
+            KeyCode::Char('/') => self.filter_input = Some(TextInput::new(&self.filter)), // search
+            KeyCode::Esc => self.set_filter(String::new()), // clears the search filter
+            KeyCode::Char('?') => self.show_help(),      // shows keybindings popup
             _ => {}
         }
     }
 
+    /// `true` while the user is typing in the search box, so every key should be sent here
+    pub fn is_typing(&self) -> bool {
+        self.filter_input.is_some()
+    }
+
+    /// edits the filter, the listing is updated as the user types
+    fn handle_filter_keys(&mut self, key: KeyEvent) {
+        let Some(input) = self.filter_input.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            // [Enter] closes the search box, leaving the filter applied
+            KeyCode::Enter => self.filter_input = None,
+            // [Esc] closes the search box and clears the filter
+            KeyCode::Esc => {
+                self.filter_input = None;
+                self.set_filter(String::new());
+            }
+            _ => {
+                if input.handle_keys(key) {
+                    let filter = input.get_value();
+                    self.set_filter(filter);
+                }
+            }
+        }
+    }
+
+    /// shows only the entries matching [filter](String), keeping the cursor on the same entry if
+    /// it still matches
+    fn set_filter(&mut self, filter: String) {
+        if filter == self.filter {
+            return;
+        }
+        self.filter = filter;
+
+        let selected = self.curr_sel_entry();
+        let curr_displaying_dir = self.get_curr_displaying_dir();
+        self.initialize(Some(curr_displaying_dir), Some(Selection::Entry(selected)));
+    }
+
+    /// returns where [filter](str) is in [name](str), as the index of the first char and the
+    /// number of chars matched
+    ///
+    /// smart case: the filter only matches case sensitively if it has some uppercase char
+    fn find_match(name: &str, filter: &str) -> Option<(usize, usize)> {
+        let case_sensitive = filter.chars().any(|c| c.is_uppercase());
+        let normalize = |c: &char| match case_sensitive {
+            true => c.to_string(),
+            false => c.to_lowercase().to_string(),
+        };
+
+        let name: Vec<String> = name.chars().map(|c| normalize(&c)).collect();
+        let filter: Vec<String> = filter.chars().map(|c| normalize(&c)).collect();
+
+        if filter.len() > name.len() {
+            return None;
+        }
+
+        (0..=name.len() - filter.len())
+            .find(|start| name[*start..*start + filter.len()] == filter[..])
+            .map(|start| (start, filter.len()))
+    }
+
     fn show_help(&mut self) {
         self.needs_redraw = true;
-        let _ = show_info("Keybindings", popup::popup::KEYBINDINGS_INFO.to_string());
+        show_info("Keybindings", popup::popup::KEYBINDINGS_INFO.to_string());
+    }
+
+    /// asks the user for the path of a directory and opens it
+    /// the path can be absolute, relative to the directory being displayed or start with `~`, and
+    /// can have environment variables
+    fn jump_to_path(&mut self) {
+        let base = self.get_curr_displaying_dir();
+        self.needs_redraw = true;
+
+        let Some(input) = show_input_with_completion(
+            "Go to",
+            TextInput::new(""),
+            |input| match input.is_empty() {
+                true => Some("Path can't be empty".to_string()),
+                false => None,
+            },
+            |input| FilePicker::complete_dir_path(input, &base),
+        ) else {
+            return; // the user cancelled
+        };
+
+        match Dir::expand_path(&input, &base).and_then(fs::canonicalize) {
+            Ok(path) if path.is_dir() => self.change_curr_dir(path, None),
+            Ok(path) => show_info(
+                "Can't open directory",
+                Dir::get_shortened_path(path) + " is not a directory",
+            ),
+            Err(error) => show_info("Can't open directory", input + ": " + &error.to_string()),
+        }
+    }
+
+    /// asks the user for a directory visited before, ranked by frecency, and opens it
+    fn jump_to_visited(&mut self) {
+        self.needs_redraw = true;
+        if let Some(path) = DirJump::show(self.get_curr_displaying_dir()) {
+            self.change_curr_dir(path, None);
+        }
+    }
+
+    /// completes the last component of [input](str) with the name of a directory in the path
+    /// before it, returning the completed input and, if there's more than one, the directories
+    /// that could complete it
+    /// hidden directories are only completed if the name being completed starts with `.`
+    fn complete_dir_path(input: &str, base: &Path) -> (String, Vec<String>) {
+        if input == "~" {
+            return ("~/".to_string(), Vec::new());
+        }
+
+        let (prefix, partial) = match input.rfind('/') {
+            Some(index) => input.split_at(index + 1),
+            None => ("", input),
+        };
+
+        let Ok(entries) = Dir::expand_path(prefix, base).and_then(fs::read_dir) else {
+            return (input.to_string(), Vec::new());
+        };
+
+        let names: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with(partial))
+            .filter(|name| !name.starts_with('.') || partial.starts_with('.'))
+            .sorted()
+            .collect();
+
+        match names.len() {
+            0 => (input.to_string(), Vec::new()),
+            1 => (prefix.to_string() + &names[0] + "/", Vec::new()),
+            _ => {
+                // completes as much as all of them have in common
+                let common: String = names[0]
+                    .chars()
+                    .enumerate()
+                    .take_while(|(index, c)| {
+                        names
+                            .iter()
+                            .all(|name| name.chars().nth(*index) == Some(*c))
+                    })
+                    .map(|(_, c)| c)
+                    .collect();
+                (prefix.to_string() + &common, names)
+            }
+        }
+    }
+
+    /// opens the fuzzy finder over the directory being displayed and jumps to the entry chosen
+    fn find_entry(&mut self) {
+        self.needs_redraw = true;
+        let root = self.get_curr_displaying_dir();
+        if let Some(path) = FuzzyFinder::show(root.clone()) {
+            let parent = path.parent().unwrap_or(&root).to_path_buf();
+            self.change_curr_dir(parent, Some(Selection::Entry(path)));
+        }
+    }
+
+    /// asks the user for a pattern and lists the lines matching it in the files under the
+    /// directory being displayed, jumping to the file of the hit chosen
+    /// the pattern is taken literally unless [is_regex](bool)
+    fn search_in_files(&mut self, is_regex: bool) {
+        self.needs_redraw = true;
+        let root = self.get_curr_displaying_dir();
+
+        // smart case, like the filter
+        let build = |input: &str| {
+            let pattern = match is_regex {
+                true => input.to_string(),
+                false => regex::escape(input),
+            };
+            RegexBuilder::new(&pattern)
+                .case_insensitive(!input.chars().any(|c| c.is_uppercase()))
+                .build()
+        };
+
+        let Some(pattern) = show_input(
+            if is_regex {
+                "Search regex in files"
+            } else {
+                "Search in files"
+            },
+            TextInput::new(""),
+            |pattern| match pattern.is_empty() {
+                true => Some("Pattern can't be empty".to_string()),
+                false => build(pattern).err().map(|error| error.to_string()),
+            },
+        ) else {
+            return; // the user cancelled
+        };
+
+        let Ok(regex) = build(&pattern) else { return };
+
+        if let Some(path) = GrepView::show(root.clone(), regex, self.jobs.clone()) {
+            let parent = path.parent().unwrap_or(&root).to_path_buf();
+            self.change_curr_dir(parent, Some(Selection::Entry(path)));
+        }
+    }
+
+    /// waits for the key of a bookmark sequence, [Esc] or anything that isn't a char cancels it
+    fn read_bookmark_key() -> Option<char> {
+        loop {
+            // it's important to check that the event is a key press event as
+            // crossterm also emits key release and repeat events on Windows.
+            match event::read().ok()? {
+                Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                    return match key_event.code {
+                        KeyCode::Char(c) if !c.is_whitespace() => Some(c),
+                        _ => None,
+                    };
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// bookmarks the directory being displayed under [key](char), replacing what was there
+    fn set_bookmark(&mut self, key: char) {
+        let dir = self.get_curr_displaying_dir();
+        // try catch
+        if let Err(error) = (|| -> Result<()> {
+            let mut bookmarks = Bookmarks::load()?;
+            bookmarks.set(key, dir);
+            bookmarks.save()
+        })() {
+            self.needs_redraw = true;
+            show_info("Error saving bookmark", error.to_string());
+        }
+    }
+
+    /// opens the directory bookmarked under [key](char)
+    fn jump_to_bookmark(&mut self, key: char) {
+        let bookmarks = match Bookmarks::load() {
+            Ok(bookmarks) => bookmarks,
+            Err(error) => {
+                self.needs_redraw = true;
+                show_info("Error reading bookmarks", error.to_string());
+                return;
+            }
+        };
+
+        match bookmarks.get(key) {
+            Some(bookmark) if bookmark.path.is_dir() => {
+                self.change_curr_dir(bookmark.path.clone(), None)
+            }
+            Some(bookmark) => {
+                self.needs_redraw = true;
+                show_info(
+                    "Can't open bookmark",
+                    Dir::get_shortened_path(bookmark.path.clone()) + " doesn't exist",
+                )
+            }
+            None => {
+                self.needs_redraw = true;
+                show_info(
+                    "No bookmark",
+                    "Nothing is bookmarked under '".to_string() + &key.to_string() + "'",
+                )
+            }
+        }
+    }
+
+    /// opens the bookmarks view and jumps to the bookmark chosen
+    fn show_bookmarks(&mut self) {
+        self.needs_redraw = true;
+        if let Some(path) = BookmarksView::show() {
+            self.change_curr_dir(path, None);
+        }
+    }
+
+    /// opens the trash view, entries restored there may show up in the current directory
+    fn show_trash(&mut self) {
+        self.needs_redraw = true;
+        TrashView::show(self.jobs.clone());
+        let curr_displaying_dir = self.get_curr_displaying_dir();
+        self.initialize(
+            Some(curr_displaying_dir),
+            Some(Selection::Index(self.index)),
+        );
     }
 
     fn select_first(&mut self) {
@@ -183,7 +586,7 @@ impl FilePicker {
     fn open_selected_dir(&mut self) {
         let current_selected = self.curr_sel_entry();
         if current_selected.is_dir() {
-            self.change_curr_dir(current_selected);
+            self.change_curr_dir(current_selected, None);
         }
     }
 
@@ -196,16 +599,237 @@ impl FilePicker {
             self.buffer.push(value.clone()) // adds it if not
         }
         let curr_displaying_dir = self.get_curr_displaying_dir();
-        self.initialize(Some(curr_displaying_dir), Some(self.index));
+        self.initialize(
+            Some(curr_displaying_dir),
+            Some(Selection::Index(self.index)),
+        );
+    }
+
+    fn trash_sel_entry(&mut self) {
+        // the parent entry is never trashed by accident with a single key
+        if self.index > 0 {
+            let curr_sel = self.curr_sel_entry();
+            self.trash_files(vec![curr_sel]);
+        }
+    }
+
+    /// moves all directory entries passed as argument to the trash, in the background
+    /// no confirmation is needed since they can be restored
+    fn trash_files(&mut self, files: Vec<PathBuf>) {
+        if !files.is_empty() {
+            self.jobs.push(JobKind::Trash(files));
+        }
+    }
+
+    /// asks the user for a new name for the entry under the cursor and renames it
+    fn rename_sel_entry(&mut self) {
+        // the parent entry is not a real entry of the directory
+        if self.index == 0 {
+            return;
+        }
+
+        let path = self.curr_sel_entry();
+        let name = Dir::get_entry_name(path.clone());
+        let parent = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+
+        // only the name without the extension is selected, so typing keeps the extension
+        let stem = match path.is_dir() {
+            true => None,
+            false => Path::new(&name).file_stem(),
+        };
+        let selection_end = stem
+            .map(|stem| stem.to_string_lossy().chars().count())
+            .unwrap_or(name.chars().count());
+
+        self.needs_redraw = true;
+
+        let Some(new_name) = show_input(
+            "Rename",
+            TextInput::new(&name).with_selection(0, selection_end),
+            |new_name| FilePicker::validate_name(&parent, new_name, Some(&name)),
+        ) else {
+            return; // the user cancelled
+        };
+
+        if new_name == name {
+            return;
+        }
+
+        let dest = parent.join(&new_name);
+        if let Err(error) = fs::rename(&path, &dest) {
+            show_info("Error renaming entry", error.to_string());
+            return;
+        }
+
+        // the buffer keeps pointing to the entry under its new name
+        for entry in self.buffer.iter_mut() {
+            if *entry == path {
+                *entry = dest.clone();
+            }
+        }
+        self.jobs.record_changes(vec![Change::Moved {
+            from: path,
+            to: dest.clone(),
+        }]);
+
+        let curr_displaying_dir = self.get_curr_displaying_dir();
+        self.initialize(Some(curr_displaying_dir), Some(Selection::Entry(dest)));
+    }
+
+    /// renames every entry in the buffer by editing their names in the user's editor
+    /// the renames are only done after the user confirms them
+    fn bulk_rename_buffer(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let entries = self.buffer.clone();
+        let names: Vec<String> = entries
+            .iter()
+            .map(|entry| Dir::get_entry_name(entry.clone()))
+            .collect();
+
+        self.needs_redraw = true;
+
+        let renames = match BulkRename::edit_names(&names) {
+            Ok(Some(new_names)) => match BulkRename::plan(&entries, &new_names) {
+                Ok(renames) => renames,
+                Err(error) => {
+                    show_info("Can't rename entries", error);
+                    return;
+                }
+            },
+            Ok(None) => return, // the editor was closed with an error
+            Err(error) => {
+                show_info("Error editing names", error.to_string());
+                return;
+            }
+        };
+
+        if renames.is_empty()
+            || !show_confirmation("Rename?", BulkRename::get_diff_to_display(&renames))
+        {
+            return;
+        }
+
+        let (changes, errors) = BulkRename::apply(&renames);
+
+        // the buffer keeps pointing to the renamed entries
+        for entry in self.buffer.iter_mut() {
+            for change in changes.iter() {
+                if let Change::Moved { from, to } = change {
+                    if entry == from {
+                        *entry = to.clone();
+                    }
+                }
+            }
+        }
+        self.jobs.record_changes(changes);
+
+        let curr_displaying_dir = self.get_curr_displaying_dir();
+        self.initialize(
+            Some(curr_displaying_dir),
+            Some(Selection::Index(self.index)),
+        );
+
+        if !errors.is_empty() {
+            show_info(
+                "Error renaming entries",
+                errors
+                    .iter()
+                    .map(|(entry, error)| {
+                        Dir::get_entry_name(entry.clone()) + ": " + &error.to_string()
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
+        }
+    }
+
+    /// asks the user for the path of a new empty file, or directory if [is_dir](bool), and creates
+    /// it inside the directory being displayed
+    /// paths like `a/b/c` create the missing directories on the way, like `mkdir -p`
+    fn create_entry(&mut self, is_dir: bool) {
+        let dir = self.get_curr_displaying_dir();
+        self.needs_redraw = true;
+
+        let Some(input) = show_input(
+            if is_dir { "New directory" } else { "New file" },
+            TextInput::new(""),
+            |input| FilePicker::validate_new_path(&dir, input, is_dir),
+        ) else {
+            return; // the user cancelled
+        };
+
+        let path = dir.join(input.trim_end_matches('/'));
+        match FileOps::create_entry(&path, is_dir) {
+            Ok(top) => {
+                self.jobs.record_changes(vec![Change::Created {
+                    top: top.clone(),
+                    path,
+                    is_dir,
+                }]);
+                // the cursor goes to the entry that shows up in the directory being displayed
+                self.initialize(Some(dir), Some(Selection::Entry(top)));
+            }
+            Err(error) => {
+                show_info("Error creating entry", error.to_string());
+                self.initialize(Some(dir), Some(Selection::Index(self.index)));
+            }
+        }
+    }
+
+    /// returns why [input](str) can't be used as the path of a new entry inside [dir](Path), or
+    /// `None` if it can
+    fn validate_new_path(dir: &Path, input: &str, is_dir: bool) -> Option<String> {
+        let path = Path::new(input);
+        if input.is_empty() {
+            Some("Name can't be empty".to_string())
+        } else if path.is_absolute() {
+            Some("Path must be relative to the current directory".to_string())
+        } else if path
+            .components()
+            .any(|component| component.as_os_str() == "..")
+        {
+            Some("Path can't go outside the current directory".to_string())
+        } else if !is_dir && input.ends_with('/') {
+            Some("File name can't end with '/'".to_string())
+        } else if dir.join(input).symlink_metadata().is_ok() {
+            Some(input.to_string() + " already exists")
+        } else {
+            None
+        }
+    }
+
+    /// returns why [name](str) can't be given to an entry in [dir](Path), or `None` if it can
+    /// [current](Option<&str>) is the name the entry already has, which is always valid
+    fn validate_name(dir: &Path, name: &str, current: Option<&str>) -> Option<String> {
+        if name.is_empty() {
+            Some("Name can't be empty".to_string())
+        } else if name.contains('/') {
+            Some("Name can't contain '/'".to_string())
+        } else if name == "." || name == ".." {
+            Some("Name can't be '.' or '..'".to_string())
+        } else if current == Some(name) {
+            None
+        } else if dir.join(name).symlink_metadata().is_ok() {
+            Some(name.to_string() + " already exists")
+        } else {
+            None
+        }
     }
 
     fn delete_sel_entry(&mut self) {
-        let curr_sel = self.curr_sel_entry();
-        self.delete_files(vec![curr_sel]);
+        // same as trashing, the parent entry can't be deleted from inside it
+        if self.index > 0 {
+            let curr_sel = self.curr_sel_entry();
+            self.delete_files(vec![curr_sel]);
+        }
     }
 
-    /// deletes with confirmation all directory entries passed as argument
-    fn delete_files(&mut self, files: Vec<PathBuf>) -> bool {
+    /// permanently deletes with confirmation all directory entries passed as argument
+    /// the deletion itself runs in the background
+    fn delete_files(&mut self, files: Vec<PathBuf>) {
         let mut files_string: String = "".to_string();
 
         // builds the list of files that will be deleted to display to the user
@@ -220,60 +844,147 @@ impl FilePicker {
 
         self.needs_redraw = true;
 
-        //poor man try catch
-        match (|| -> Result<bool> {
-            if show_confirmation("Confirm deletion?", files_string) {
-                // deletes all file(s) sent as argument
-                for entry in files {
-                    if entry.clone().is_dir() {
-                        remove_dir_all(entry)?;
-                    } else if entry.is_file() {
-                        remove_file(entry)?;
+        if show_confirmation("Delete permanently?", files_string) {
+            self.jobs.push(JobKind::Delete(files));
+        }
+    }
+
+    /// updates the widget after a background job finished changing the file system
+    /// entries the job is done with are removed from the buffer
+    pub fn job_finished(&mut self, report: &JobReport) {
+        self.buffer
+            .retain(|entry| !report.completed.contains(entry));
+        let curr_displaying_dir = self.get_curr_displaying_dir();
+        self.initialize(
+            Some(curr_displaying_dir),
+            Some(Selection::Index(self.index)),
+        );
+    }
+
+    /// copies or moves every entry in the buffer into the directory being displayed
+    /// asks the user what to do for each entry whose name is already taken there
+    ///
+    /// the paste itself runs in the background, once it's done the entries moved successfully
+    /// are removed from the buffer
+    fn paste_buffer(&mut self, mode: PasteMode) {
+        let dest_dir = self.get_curr_displaying_dir();
+        self.needs_redraw = true;
+
+        // once the user chooses to apply a resolution to all remaining conflicts it stops asking
+        let mut apply_to_all: Option<ConflictResolution> = None;
+        let plan = FileOps::plan_paste(
+            self.buffer.clone(),
+            dest_dir.clone(),
+            mode,
+            &mut |src, dest| {
+                if apply_to_all.is_none() {
+                    let info = Dir::get_entry_name(dest.to_path_buf())
+                        + if dest.is_dir() { "/" } else { "" }
+                        + match dest.symlink_metadata() {
+                            Ok(_) => " already exists in ",
+                            // an earlier entry of the same paste goes there
+                            Err(_) => " is already being pasted into ",
+                        }
+                        + &Dir::get_shortened_path(Dir::get_parent_dir(dest.to_path_buf()).pathbuf)
+                        + "\n\nsource: "
+                        + &Dir::get_shortened_path(src.to_path_buf());
+                    let (resolution, to_all) = show_conflict("Entry already exists", info)?;
+                    if !to_all {
+                        return Some(resolution);
                     }
+                    apply_to_all = Some(resolution);
                 }
-                // reinitializes the directory list
-                let curr_displaying_dir = self.get_curr_displaying_dir();
-                self.initialize(
-                    Some(curr_displaying_dir),
-                    if self.index > 2 {
-                        // puts the cursor on the item above the one deleted
-                        Some(self.index - 1)
-                    } else {
-                        None
-                    },
-                );
-
-                Ok(true)
-            } else {
-                Ok(false)
-            }
-        })() {
-            Err(error) => {
-                show_error("Error deleting file", error);
-                self.needs_redraw = true;
-                false
-            }
-            Ok(res) => res,
+                apply_to_all
+            },
+        );
+
+        // the user cancelled the paste
+        let Some(plan) = plan else { return };
+
+        if !plan.errors.is_empty() {
+            let errors = plan
+                .errors
+                .iter()
+                .map(|(entry, error)| {
+                    Dir::get_entry_name(entry.clone()) + ": " + &error.to_string()
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            show_info(
+                match mode {
+                    PasteMode::Copy => "Error copying files",
+                    PasteMode::Move => "Error moving files",
+                },
+                errors,
+            );
+        }
+
+        // the buffer is updated once the job reports back
+        if !plan.steps.is_empty() {
+            self.jobs.push(JobKind::Paste {
+                steps: plan.steps,
+                mode,
+            });
         }
     }
 
+    /// opens the parent directory with the cursor on the directory being left
     fn up_dir(&mut self) {
-        let curr = Dir::get_cur_dir().pathbuf;
+        let curr = self.get_curr_displaying_dir();
         let parent = Dir::get_parent_dir(curr.clone()).pathbuf;
         if parent != curr {
-            self.change_curr_dir(Dir::get_parent_dir(Dir::get_cur_dir().pathbuf).pathbuf);
+            self.change_curr_dir(parent, Some(Selection::Entry(curr)));
+        }
+    }
+
+    /// goes back to the previous directory visited, with the cursor where it was left
+    fn go_back(&mut self) {
+        let curr = self.get_curr_visit();
+        if let Some(visit) = self.history.go_back(curr) {
+            self.open_dir(visit.dir, Some(Selection::Entry(visit.selected)));
+        }
+    }
+
+    /// goes forward to the directory left with [FilePicker::go_back]
+    fn go_forward(&mut self) {
+        let curr = self.get_curr_visit();
+        if let Some(visit) = self.history.go_forward(curr) {
+            self.open_dir(visit.dir, Some(Selection::Entry(visit.selected)));
+        }
+    }
+
+    fn get_curr_visit(&mut self) -> Visit {
+        Visit {
+            dir: self.get_curr_displaying_dir(),
+            selected: self.curr_sel_entry(),
         }
     }
 
     /// returns current directory being displayed in the list
     /// *not* the apllication working directory
-    fn get_curr_displaying_dir(&mut self) -> PathBuf {
-        Dir::get_parent_dir(self.curr_sel_entry()).pathbuf
+    pub fn get_curr_displaying_dir(&self) -> PathBuf {
+        self.curr_dir.clone()
+    }
+
+    /// opens [path](PathBuf) with the cursor on the entry chosen by [selection](Option<Selection>)
+    /// the directory being left is added to the history
+    fn change_curr_dir(&mut self, path: PathBuf, selection: Option<Selection>) {
+        if path != self.curr_dir {
+            let curr = self.get_curr_visit();
+            self.history.push(curr);
+        }
+        self.open_dir(path, selection);
     }
 
-    fn change_curr_dir(&mut self, path: PathBuf) {
-        Dir::change_working_dir(path.clone());
-        self.initialize(Some(path.clone()), None);
+    /// opens [path](PathBuf) without adding anything to the history
+    fn open_dir(&mut self, path: PathBuf, selection: Option<Selection>) {
+        // the filter is about the entries of the directory being left
+        self.filter.clear();
+        self.filter_input = None;
+        // only directories that could actually be opened count as visited
+        if self.initialize(Some(path.clone()), selection) {
+            Frecency::record_visit(&path);
+        }
     }
 }
 
@@ -288,11 +999,53 @@ impl Widget for &FilePicker {
             style.selected = style.selected.add_modifier(Modifier::DIM);
         }
 
+        // the search box takes the last line while it's open or a filter is applied
+        let (list_area, filter_area) = match self.filter_input.is_some() || !self.filter.is_empty()
+        {
+            true => {
+                let layout = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(vec![Constraint::Fill(1), Constraint::Length(1)])
+                    .split(area);
+                (layout[0], Some(layout[1]))
+            }
+            false => (area, None),
+        };
+
         let list = List::new(
             self.items
                 .iter()
-                .map(|dir| dir.display_name.clone())
-                .collect::<Vec<String>>(),
+                .enumerate()
+                .map(|(index, dir)| {
+                    // the parent entry is shown even if it doesn't match
+                    let found = match index {
+                        0 => None,
+                        _ => FilePicker::find_match(
+                            &Dir::get_entry_name(dir.pathbuf.clone()),
+                            &self.filter,
+                        )
+                        .filter(|(_, len)| *len > 0),
+                    };
+                    let Some((start, len)) = found else {
+                        return Line::from(dir.display_name.clone());
+                    };
+
+                    // the name is at the end of the display name, after the icon
+                    let chars: Vec<char> = dir.display_name.chars().collect();
+                    let name_start =
+                        chars.len() - Dir::get_entry_name(dir.pathbuf.clone()).chars().count();
+                    let start = name_start + start;
+
+                    Line::from(vec![
+                        Span::raw(chars[..start].iter().collect::<String>()),
+                        Span::styled(
+                            chars[start..start + len].iter().collect::<String>(),
+                            style.matched,
+                        ),
+                        Span::raw(chars[start + len..].iter().collect::<String>()),
+                    ])
+                })
+                .collect::<Vec<Line>>(),
         )
         .style(style.normal)
         .highlight_style(style.selected);
@@ -300,6 +1053,100 @@ impl Widget for &FilePicker {
         // necessary for catching events
         let mut state = ListState::default().with_selected(Some(self.index));
 
-        StatefulWidget::render(list, area, buf, &mut state);
+        StatefulWidget::render(list, list_area, buf, &mut state);
+
+        if let Some(filter_area) = filter_area {
+            let [prompt_area, input_area] = *Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Length(1), Constraint::Fill(1)])
+                .split(filter_area)
+            else {
+                return;
+            };
+
+            Paragraph::new("/").render(prompt_area, buf);
+            match &self.filter_input {
+                Some(input) => input.render(input_area, buf),
+                None => Paragraph::new(self.filter.clone())
+                    .style(style.normal.add_modifier(Modifier::DIM))
+                    .render(input_area, buf),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    #[test]
+    fn lowercase_filters_ignore_case() {
+        assert_eq!(FilePicker::find_match("ReadMe.md", "readme"), Some((0, 6)));
+        assert_eq!(FilePicker::find_match("notes.TXT", "txt"), Some((6, 3)));
+    }
+
+    #[test]
+    fn filters_with_uppercase_match_case() {
+        assert_eq!(FilePicker::find_match("ReadMe.md", "Me"), Some((4, 2)));
+        assert_eq!(FilePicker::find_match("readme.md", "Me"), None);
+    }
+
+    #[test]
+    fn matches_are_counted_in_chars() {
+        assert_eq!(FilePicker::find_match("añob.txt", "ob"), Some((2, 2)));
+        assert_eq!(FilePicker::find_match("ab", "abc"), None);
+        assert_eq!(FilePicker::find_match("ab", ""), Some((0, 0)));
+    }
+
+    #[test]
+    fn completes_the_only_directory_that_fits() {
+        let dir = TestDir::new("complete-one");
+        dir.write("docs/a.txt", "");
+        dir.write("dots.txt", "");
+
+        assert_eq!(
+            FilePicker::complete_dir_path("do", &dir.path),
+            ("docs/".to_string(), Vec::new())
+        );
+        assert_eq!(
+            FilePicker::complete_dir_path("docs/", &dir.path),
+            ("docs/".to_string(), Vec::new())
+        );
+        assert_eq!(
+            FilePicker::complete_dir_path("x", &dir.path),
+            ("x".to_string(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn completes_what_every_directory_has_in_common() {
+        let dir = TestDir::new("complete-many");
+        dir.write("src/project-a/a.txt", "");
+        dir.write("src/project-b/b.txt", "");
+
+        assert_eq!(
+            FilePicker::complete_dir_path("src/p", &dir.path),
+            (
+                "src/project-".to_string(),
+                vec!["project-a".to_string(), "project-b".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn hidden_directories_are_completed_only_when_asked() {
+        let dir = TestDir::new("complete-hidden");
+        dir.write(".config/a.txt", "");
+        dir.write("code/a.txt", "");
+
+        assert_eq!(
+            FilePicker::complete_dir_path("", &dir.path),
+            ("code/".to_string(), Vec::new())
+        );
+        assert_eq!(
+            FilePicker::complete_dir_path(".", &dir.path),
+            (".config/".to_string(), Vec::new())
+        );
     }
 }
@@ -0,0 +1,393 @@
+//! Directories ranked by how often and how recently they were visited, kept between sessions
+//!
+//! Works like zoxide: every visit adds 1 to the rank of the directory, and the score used to sort
+//! them is the rank weighted by how long ago the last visit was. Once the ranks add up to more than
+//! [MAX_TOTAL_RANK] all of them are scaled down and the ones that fall under 1 are forgotten, so
+//! directories that aren't visited anymore slowly go away.
+//!
+//! The database is saved in `$XDG_DATA_HOME/navfs/frecency`, one directory per line as the rank,
+//! the last visit as seconds since the epoch and the path, separated by tabs. Visits are kept in
+//! memory for a few seconds and written together on a background task, so changing directories
+//! never waits for the file.
+//!
+//! # Example
+//! ```rust
+//! Frecency::record_visit(Path::new("/home/user/projects/navfs"));
+//!
+//! // before exiting, so the visits still in memory aren't lost
+//! Frecency::save_pending();
+//!
+//! // the best match for the query is the first one
+//! for (path, score) in Frecency::load()?.query("proj nav", &curr_dir) {
+//!     println!("{score:.1} {}", path.display());
+//! }
+//! ```
+
+use std::{
+    fs,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::runtime::Handle;
+
+use crate::ui::utils::Utils;
+
+/// name of the file in `$XDG_DATA_HOME/navfs`
+const FRECENCY_FILE: &str = "frecency";
+/// the ranks are aged once they add up to more than this
+const MAX_TOTAL_RANK: f64 = 10_000.0;
+/// how long visits wait in memory before being saved, the ones made meanwhile are saved with them
+const SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// visits not saved yet, as the directory and when it was visited
+static PENDING: Mutex<Vec<(PathBuf, u64)>> = Mutex::new(Vec::new());
+/// held while saving, so two saves never read and write the file at the same time
+static SAVING: Mutex<()> = Mutex::new(());
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// a directory in the database
+///
+/// [path](PathBuf): absolute path of the directory
+/// [rank](f64): how many times it was visited, scaled down when the database is aged
+/// [last_visit](u64): when it was last visited, in seconds since the epoch
+pub struct Visits {
+    pub path: PathBuf,
+    pub rank: f64,
+    pub last_visit: u64,
+}
+
+/// [items](Vec<Visits>): every directory in the database
+pub struct Frecency {
+    items: Vec<Visits>,
+}
+
+impl Frecency {
+    /// reads the database, including the visits that weren't saved yet
+    pub fn load() -> Result<Frecency> {
+        let mut frecency = Frecency::load_saved()?;
+        if let Ok(pending) = PENDING.lock() {
+            for (path, time) in pending.iter() {
+                frecency.add_visit(path, *time);
+            }
+        }
+        Ok(frecency)
+    }
+
+    /// reads the database file, it's empty if the file doesn't exist yet
+    /// malformed lines are ignored
+    fn load_saved() -> Result<Frecency> {
+        let content = match fs::read_to_string(Utils::get_data_file(FRECENCY_FILE)) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+
+        let items = content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let rank = fields.next()?.parse::<f64>().ok()?;
+                let last_visit = fields.next()?.parse::<u64>().ok()?;
+                let path = PathBuf::from(fields.next()?);
+                Some(Visits {
+                    path,
+                    rank,
+                    last_visit,
+                })
+            })
+            .collect();
+
+        Ok(Frecency { items })
+    }
+
+    /// writes the database to the file
+    pub fn save(&self) -> Result<()> {
+        let mut content = String::new();
+        for visits in &self.items {
+            let path = visits.path.to_string_lossy();
+            // it would break the line format, such directories are just not remembered
+            if path.contains('\n') {
+                continue;
+            }
+            content = content
+                + &visits.rank.to_string()
+                + "\t"
+                + &visits.last_visit.to_string()
+                + "\t"
+                + &path
+                + "\n";
+        }
+
+        Utils::write_data_file(FRECENCY_FILE, &content)
+    }
+
+    /// adds a visit to [path](Path), saved to the database file a few seconds later on a
+    /// background task
+    pub fn record_visit(path: &Path) {
+        let Ok(mut pending) = PENDING.lock() else {
+            return;
+        };
+        pending.push((path.to_path_buf(), Frecency::now()));
+
+        // the first visit waiting schedules the save for every visit made until then
+        if pending.len() == 1 {
+            drop(pending);
+            match Handle::try_current() {
+                Ok(runtime) => {
+                    runtime.spawn_blocking(|| {
+                        thread::sleep(SAVE_DELAY);
+                        Frecency::save_pending();
+                    });
+                }
+                Err(_) => Frecency::save_pending(),
+            }
+        }
+    }
+
+    /// adds the visits that weren't saved yet to the database file
+    /// errors are ignored, failing to remember a visit is not worth bothering the user with
+    pub fn save_pending() {
+        let Ok(_saving) = SAVING.lock() else {
+            return;
+        };
+        let pending: Vec<(PathBuf, u64)> = match PENDING.lock() {
+            Ok(mut pending) => pending.drain(..).collect(),
+            Err(_) => return,
+        };
+        if pending.is_empty() {
+            return;
+        }
+
+        if let Ok(mut frecency) = Frecency::load_saved() {
+            for (path, time) in pending {
+                frecency.add_visit(&path, time);
+            }
+            let _ = frecency.save();
+        }
+    }
+
+    /// adds a visit to [path](Path) made at [time](u64), in seconds since the epoch
+    fn add_visit(&mut self, path: &Path, time: u64) {
+        match self.items.iter_mut().find(|visits| visits.path == path) {
+            Some(visits) => {
+                visits.rank += 1.0;
+                visits.last_visit = visits.last_visit.max(time);
+            }
+            None => self.items.push(Visits {
+                path: path.to_path_buf(),
+                rank: 1.0,
+                last_visit: time,
+            }),
+        }
+        self.age();
+    }
+
+    /// returns the directories matching [query](str), best score first
+    /// [exclude](Path) is left out, it's usually the directory the user is already in, and so are
+    /// the directories that don't exist anymore
+    pub fn query(&self, query: &str, exclude: &Path) -> Vec<(&PathBuf, f64)> {
+        let keywords = Frecency::get_keywords(query);
+        let now = Frecency::now();
+
+        let mut results: Vec<(&PathBuf, f64)> = self
+            .items
+            .iter()
+            .filter(|visits| visits.path != exclude)
+            .filter(|visits| Frecency::matches(&visits.path, &keywords))
+            .filter(|visits| visits.path.is_dir())
+            .map(|visits| (&visits.path, Frecency::get_score(visits, now)))
+            .collect();
+
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results
+    }
+
+    /// splits [query](str) in lowercase keywords
+    pub fn get_keywords(query: &str) -> Vec<String> {
+        query.split_whitespace().map(str::to_lowercase).collect()
+    }
+
+    /// like zoxide, [path](Path) matches if the [keywords](Vec<String>) show up in it in order, and
+    /// the last one is in the name of the directory
+    fn matches(path: &Path, keywords: &[String]) -> bool {
+        let Some(last) = keywords.last() else {
+            return true; // an empty query matches everything
+        };
+
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if !name.contains(last.as_str()) {
+            return false;
+        }
+
+        Frecency::find_keywords(&path.to_string_lossy(), keywords).is_some()
+    }
+
+    /// returns where each keyword of [keywords](Vec<String>) is in [text](str), as the index of
+    /// its first char and its length in chars, if all of them are there in order
+    pub fn find_keywords(text: &str, keywords: &[String]) -> Option<Vec<(usize, usize)>> {
+        let text = text.to_lowercase();
+        let mut positions = Vec::with_capacity(keywords.len());
+        let mut from = 0;
+
+        for keyword in keywords {
+            let start = from + text[from..].find(keyword.as_str())?;
+            positions.push((text[..start].chars().count(), keyword.chars().count()));
+            from = start + keyword.len();
+        }
+
+        Some(positions)
+    }
+
+    /// the rank weighted by how recent the last visit was, the same way zoxide does
+    fn get_score(visits: &Visits, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(visits.last_visit);
+        let weight = if elapsed < HOUR {
+            4.0
+        } else if elapsed < DAY {
+            2.0
+        } else if elapsed < WEEK {
+            0.5
+        } else {
+            0.25
+        };
+        visits.rank * weight
+    }
+
+    /// scales the ranks down once they add up to too much, forgetting the directories that end up
+    /// with a rank under 1
+    fn age(&mut self) {
+        let total: f64 = self.items.iter().map(|visits| visits.rank).sum();
+        if total <= MAX_TOTAL_RANK {
+            return;
+        }
+
+        let factor = 0.9 * MAX_TOTAL_RANK / total;
+        for visits in self.items.iter_mut() {
+            visits.rank *= factor;
+        }
+        self.items.retain(|visits| visits.rank >= 1.0);
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    fn get_rank(frecency: &Frecency, path: &Path) -> Option<f64> {
+        frecency
+            .items
+            .iter()
+            .find(|visits| visits.path == path)
+            .map(|visits| visits.rank)
+    }
+
+    #[test]
+    fn visits_add_to_the_rank() {
+        let mut frecency = Frecency { items: Vec::new() };
+        let path = Path::new("/a");
+
+        frecency.add_visit(path, 10);
+        frecency.add_visit(path, 20);
+        // visits saved late never move the last visit back
+        frecency.add_visit(path, 15);
+
+        assert_eq!(get_rank(&frecency, path), Some(3.0));
+        assert_eq!(frecency.items[0].last_visit, 20);
+    }
+
+    #[test]
+    fn aging_forgets_directories_not_visited_anymore() {
+        let mut frecency = Frecency {
+            items: vec![
+                Visits {
+                    path: PathBuf::from("/often"),
+                    rank: MAX_TOTAL_RANK,
+                    last_visit: 0,
+                },
+                Visits {
+                    path: PathBuf::from("/once"),
+                    rank: 1.0,
+                    last_visit: 0,
+                },
+            ],
+        };
+
+        frecency.add_visit(Path::new("/often"), 0);
+
+        let total: f64 = frecency.items.iter().map(|visits| visits.rank).sum();
+        assert!(total <= MAX_TOTAL_RANK);
+        assert!(get_rank(&frecency, Path::new("/often")).is_some());
+        assert!(get_rank(&frecency, Path::new("/once")).is_none());
+    }
+
+    #[test]
+    fn query_sorts_by_score_and_skips_what_doesnt_match() {
+        let dir = TestDir::new("frecency-query");
+        for name in ["projects/navfs", "projects/other", "navfs-old", "curr"] {
+            fs::create_dir_all(dir.path.join(name)).unwrap();
+        }
+        let now = Frecency::now();
+        let visits = |name: &str, rank: f64, last_visit: u64| Visits {
+            path: dir.path.join(name),
+            rank,
+            last_visit,
+        };
+        let frecency = Frecency {
+            items: vec![
+                // a lot of visits, but more than a week ago
+                visits("navfs-old", 10.0, now - 2 * WEEK),
+                visits("projects/navfs", 2.0, now),
+                visits("projects/other", 50.0, now),
+                visits("curr", 50.0, now),
+                visits("gone", 50.0, now),
+            ],
+        };
+
+        let names = |query: &str| -> Vec<PathBuf> {
+            frecency
+                .query(query, &dir.path.join("curr"))
+                .into_iter()
+                .map(|(path, _)| path.strip_prefix(&dir.path).unwrap().to_path_buf())
+                .collect()
+        };
+
+        assert_eq!(
+            names("navfs"),
+            vec![PathBuf::from("projects/navfs"), PathBuf::from("navfs-old")]
+        );
+        // the last keyword has to be in the name of the directory
+        assert_eq!(names("proj nav"), vec![PathBuf::from("projects/navfs")]);
+        assert!(names("navfs proj").is_empty());
+        // the directory the user is in and the ones that are gone are left out
+        assert_eq!(names("").len(), 3);
+    }
+
+    #[test]
+    fn keywords_are_found_in_order() {
+        let keywords = Frecency::get_keywords("Ño  nav");
+
+        assert_eq!(
+            Frecency::find_keywords("/años/navfs", &keywords),
+            Some(vec![(2, 2), (6, 3)])
+        );
+        assert_eq!(Frecency::find_keywords("/navfs/años", &keywords), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! Directories visited during the session, to go back and forward through them like in a browser
+//!
+//! Every directory left is remembered with the entry that was under the cursor, so going back to
+//! it puts the cursor where it was.
+//!
+//! # Example
+//! ```rust
+//! // leaving /home with the cursor on /home/user
+//! history.push(Visit {
+//!     dir: PathBuf::from("/home"),
+//!     selected: PathBuf::from("/home/user"),
+//! });
+//!
+//! if let Some(visit) = history.go_back(curr_visit) {
+//!     file_picker.initialize(Some(visit.dir), Some(Selection::Entry(visit.selected)));
+//! }
+//! ```
+
+use std::path::PathBuf;
+
+/// the history forgets the oldest directories past this
+const MAX_VISITS: usize = 100;
+
+/// a directory that was left
+///
+/// [dir](PathBuf): the directory
+/// [selected](PathBuf): the entry under the cursor when it was left
+pub struct Visit {
+    pub dir: PathBuf,
+    pub selected: PathBuf,
+}
+
+/// [back](Vec<Visit>): directories left to go to another one, the last is the most recent
+/// [forward](Vec<Visit>): directories left going back, the last is the closest one
+#[derive(Default)]
+pub struct History {
+    back: Vec<Visit>,
+    forward: Vec<Visit>,
+}
+
+impl History {
+    /// records [visit](Visit) being left for a new directory, which forgets the directories ahead,
+    /// like a browser does
+    pub fn push(&mut self, visit: Visit) {
+        self.forward.clear();
+        self.back.push(visit);
+        if self.back.len() > MAX_VISITS {
+            self.back.remove(0);
+        }
+    }
+
+    /// returns the previous directory, keeping [curr](Visit) to go forward to
+    /// directories that don't exist anymore are skipped
+    pub fn go_back(&mut self, curr: Visit) -> Option<Visit> {
+        let visit = History::pop_existing(&mut self.back)?;
+        self.forward.push(curr);
+        Some(visit)
+    }
+
+    /// returns the directory that was left going back, keeping [curr](Visit) to go back to
+    /// directories that don't exist anymore are skipped
+    pub fn go_forward(&mut self, curr: Visit) -> Option<Visit> {
+        let visit = History::pop_existing(&mut self.forward)?;
+        self.back.push(curr);
+        Some(visit)
+    }
+
+    fn pop_existing(visits: &mut Vec<Visit>) -> Option<Visit> {
+        while let Some(visit) = visits.pop() {
+            if visit.dir.is_dir() {
+                return Some(visit);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    fn visit(dir: &TestDir, name: &str) -> Visit {
+        let path = dir.path.join(name);
+        fs::create_dir_all(&path).unwrap();
+        Visit {
+            dir: path.clone(),
+            selected: path.join("entry"),
+        }
+    }
+
+    #[test]
+    fn goes_back_and_forward() {
+        let dir = TestDir::new("history");
+        let mut history = History::default();
+        history.push(visit(&dir, "a"));
+        history.push(visit(&dir, "b"));
+
+        let back = history.go_back(visit(&dir, "c")).unwrap();
+        assert_eq!(back.dir, dir.path.join("b"));
+        assert_eq!(back.selected, dir.path.join("b/entry"));
+        assert_eq!(
+            history.go_back(visit(&dir, "b")).unwrap().dir,
+            dir.path.join("a")
+        );
+        assert!(history.go_back(visit(&dir, "a")).is_none());
+
+        assert_eq!(
+            history.go_forward(visit(&dir, "a")).unwrap().dir,
+            dir.path.join("b")
+        );
+        assert_eq!(
+            history.go_forward(visit(&dir, "b")).unwrap().dir,
+            dir.path.join("c")
+        );
+        assert!(history.go_forward(visit(&dir, "c")).is_none());
+    }
+
+    #[test]
+    fn new_directories_forget_the_ones_ahead() {
+        let dir = TestDir::new("history-forward");
+        let mut history = History::default();
+        history.push(visit(&dir, "a"));
+        history.go_back(visit(&dir, "b"));
+
+        history.push(visit(&dir, "a"));
+
+        assert!(history.go_forward(visit(&dir, "c")).is_none());
+    }
+
+    #[test]
+    fn skips_directories_that_are_gone() {
+        let dir = TestDir::new("history-gone");
+        let mut history = History::default();
+        history.push(visit(&dir, "a"));
+        history.push(visit(&dir, "b"));
+        fs::remove_dir(dir.path.join("b")).unwrap();
+
+        assert_eq!(
+            history.go_back(visit(&dir, "c")).unwrap().dir,
+            dir.path.join("a")
+        );
+    }
+
+    #[test]
+    fn forgets_the_oldest_directories() {
+        let dir = TestDir::new("history-max");
+        let mut history = History::default();
+        for index in 0..=MAX_VISITS {
+            history.push(visit(&dir, &index.to_string()));
+        }
+
+        let mut oldest = None;
+        while let Some(back) = history.go_back(visit(&dir, "curr")) {
+            oldest = Some(back.dir);
+        }
+        assert_eq!(oldest, Some(dir.path.join("1")));
+    }
+}
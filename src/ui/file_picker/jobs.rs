@@ -0,0 +1,475 @@
+//! Runs long file system operations in the background
+//!
+//! Jobs are queued and executed one at a time, in order, on the tokio blocking thread pool so the
+//! ui stays responsive while big trees are copied, moved, trashed or deleted. Each job exposes a
+//! [Progress] the status bar reads from, and reports back what it did once it finishes.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let jobs = Jobs::new();
+//!
+//! jobs.push(JobKind::Delete(vec![PathBuf::from("big_folder")]));
+//!
+//! // later, on the ui loop
+//! for report in jobs.take_finished() {
+//!     println!("{} done", report.description);
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    sync::{atomic::Ordering, Arc, Mutex},
+};
+
+use itertools::Itertools;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+use crate::ui::utils::Utils;
+
+use super::{
+    dir::Dir,
+    file_ops::{FileOps, PasteAction, PasteMode, PasteStep, Progress},
+    journal::{Change, Journal},
+    trash::Trash,
+};
+
+/// the file system operations that can be run in the background
+pub enum JobKind {
+    Paste {
+        steps: Vec<PasteStep>,
+        mode: PasteMode,
+    },
+    Trash(Vec<PathBuf>),
+    Delete(Vec<PathBuf>),
+    /// permanently deletes entries from the trash, given by their path inside it
+    EmptyTrash(Vec<PathBuf>),
+    /// reverts the last operation in the journal, the one recorded last when the job starts
+    Undo,
+    /// applies again the last operation undone, the one undone last when the job starts
+    Redo,
+}
+
+/// a queued or running job, as seen by the ui
+///
+/// [description](String): human readable summary of the job
+/// [progress](Progress): counters updated while the job runs
+pub struct JobStatus {
+    pub description: String,
+    pub progress: Progress,
+}
+
+/// what a job did, sent back to the ui once it finishes
+///
+/// [description](String): same as the one in the [JobStatus]
+/// [completed](Vec<PathBuf>): entries the job was started with that can leave the buffer
+/// [errors](Vec<(PathBuf, Error)>): entries that failed and why
+/// [cancelled](bool): if the job was stopped by the user before finishing
+pub struct JobReport {
+    pub description: String,
+    pub completed: Vec<PathBuf>,
+    pub errors: Vec<(PathBuf, Error)>,
+    pub cancelled: bool,
+}
+
+/// handle to the job queue, cheap to clone and share between widgets
+///
+/// [sender](UnboundedSender): sends new jobs to the worker task
+/// [queue](Vec<JobStatus>): jobs not finished yet, the first one is the one running
+/// [finished](Vec<JobReport>): reports the ui didn't handle yet
+/// [journal](Journal): changes done by the jobs, to be undone/redone
+#[derive(Clone)]
+pub struct Jobs {
+    sender: UnboundedSender<(Arc<JobStatus>, JobKind)>,
+    queue: Arc<Mutex<VecDeque<Arc<JobStatus>>>>,
+    finished: Arc<Mutex<Vec<JobReport>>>,
+    journal: Arc<Mutex<Journal>>,
+}
+
+impl Jobs {
+    /// starts the worker task, must be called from inside the tokio runtime
+    pub fn new() -> Jobs {
+        let (sender, mut receiver) = unbounded_channel::<(Arc<JobStatus>, JobKind)>();
+        let queue: Arc<Mutex<VecDeque<Arc<JobStatus>>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let finished: Arc<Mutex<Vec<JobReport>>> = Arc::new(Mutex::new(Vec::new()));
+        let journal: Arc<Mutex<Journal>> = Arc::new(Mutex::new(Journal::default()));
+
+        let worker_queue = queue.clone();
+        let worker_finished = finished.clone();
+        let worker_journal = journal.clone();
+
+        // the worker stops once every handle (and so every sender) is dropped
+        tokio::spawn(async move {
+            while let Some((status, kind)) = receiver.recv().await {
+                let job_status = status.clone();
+                let job_journal = worker_journal.clone();
+                let report =
+                    tokio::task::spawn_blocking(move || Jobs::run(&job_status, kind, &job_journal))
+                        .await
+                        .unwrap_or_else(|error| JobReport {
+                            description: status.description.clone(),
+                            completed: Vec::new(),
+                            errors: vec![(PathBuf::new(), Error::other(error))],
+                            cancelled: false,
+                        });
+
+                // the report is stored first so the queue is never seen idle with it missing
+                if let Ok(mut finished) = worker_finished.lock() {
+                    finished.push(report);
+                }
+                if let Ok(mut queue) = worker_queue.lock() {
+                    queue.pop_front();
+                }
+            }
+        });
+
+        Jobs {
+            sender,
+            queue,
+            finished,
+            journal,
+        }
+    }
+
+    /// adds a job to the end of the queue
+    pub fn push(&self, kind: JobKind) {
+        let status = Arc::new(JobStatus {
+            description: Jobs::describe(&kind),
+            progress: Progress::default(),
+        });
+
+        if let Ok(mut queue) = self.queue.lock() {
+            queue.push_back(status.clone());
+        }
+
+        if self.sender.send((status, kind)).is_err() {
+            // the worker is gone, nothing will ever run this job
+            if let Ok(mut queue) = self.queue.lock() {
+                queue.pop_back();
+            }
+        }
+    }
+
+    /// queues a job reverting the last operation recorded in the journal
+    /// it runs after the jobs already queued, so it never undoes something older than them
+    pub fn undo(&self) {
+        self.push(JobKind::Undo);
+    }
+
+    /// queues a job applying again the last operation undone
+    pub fn redo(&self) {
+        self.push(JobKind::Redo);
+    }
+
+    /// records changes done outside of the jobs, like renames, so they can be undone too
+    pub fn record_changes(&self, changes: Vec<Change>) {
+        Jobs::record(&self.journal, changes);
+    }
+
+    /// cancels the running job and every queued one
+    pub fn cancel_all(&self) {
+        if let Ok(queue) = self.queue.lock() {
+            for status in queue.iter() {
+                status.progress.cancel();
+            }
+        }
+    }
+
+    /// `true` if no job is queued or running
+    pub fn is_idle(&self) -> bool {
+        self.queue
+            .lock()
+            .map(|queue| queue.is_empty())
+            .unwrap_or(true)
+    }
+
+    /// returns the reports of the jobs that finished since the last call
+    pub fn take_finished(&self) -> Vec<JobReport> {
+        match self.finished.lock() {
+            Ok(mut finished) => finished.drain(..).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// one line summary of the running job to be shown in the status bar
+    pub fn get_status_line(&self) -> Option<String> {
+        let queue = self.queue.lock().ok()?;
+        let running = queue.front()?;
+        let progress = &running.progress;
+
+        let entries_done = progress.entries_done.load(Ordering::Relaxed);
+        let entries_total = progress.entries_total.load(Ordering::Relaxed);
+        let bytes_done = progress.bytes_done.load(Ordering::Relaxed);
+        let bytes_total = progress.bytes_total.load(Ordering::Relaxed);
+
+        // falls back to entries for jobs that don't move any bytes around
+        let percentage = (bytes_done * 100)
+            .checked_div(bytes_total)
+            .or((entries_done * 100).checked_div(entries_total))
+            .unwrap_or(0);
+
+        let mut status = format!(
+            "{} {}% ({}/{} entries, {}/{})",
+            running.description,
+            percentage.min(100),
+            entries_done,
+            entries_total,
+            Utils::get_human_readable_size(bytes_done),
+            Utils::get_human_readable_size(bytes_total),
+        );
+
+        if progress.is_cancelled() {
+            status.push_str(" cancelling...");
+        }
+        if queue.len() > 1 {
+            status = status + " +" + &(queue.len() - 1).to_string() + " queued";
+        }
+
+        Some(status)
+    }
+
+    fn describe(kind: &JobKind) -> String {
+        let (action, entries) = match kind {
+            JobKind::Paste { steps, mode } => (
+                match mode {
+                    PasteMode::Copy => "Copying",
+                    PasteMode::Move => "Moving",
+                },
+                steps.iter().map(|step| &step.origin).unique().count(),
+            ),
+            JobKind::Trash(entries) => ("Trashing", entries.len()),
+            JobKind::Delete(entries) | JobKind::EmptyTrash(entries) => ("Deleting", entries.len()),
+            // the changes are only known once the job starts
+            JobKind::Undo => return "Undoing the last operation".to_string(),
+            JobKind::Redo => return "Redoing the last undone operation".to_string(),
+        };
+
+        match entries {
+            1 => format!("{action} 1 entry"),
+            n => format!("{action} {n} entries"),
+        }
+    }
+
+    /// executes a job on the current thread, should only be called by the worker
+    /// the changes it makes are recorded in [journal](Journal)
+    fn run(status: &JobStatus, kind: JobKind, journal: &Mutex<Journal>) -> JobReport {
+        let progress = &status.progress;
+        let mut report = JobReport {
+            description: status.description.clone(),
+            completed: Vec::new(),
+            errors: Vec::new(),
+            cancelled: false,
+        };
+
+        match kind {
+            JobKind::Paste { steps, mode } => {
+                // how much each step weights, so renames (which don't report anything while
+                // running) still move the progress forward once done
+                let sizes: Vec<(u64, u64)> = steps
+                    .iter()
+                    .map(|step| match &step.action {
+                        PasteAction::Paste { src, .. } => FileOps::measure(src),
+                        PasteAction::RemoveMergedDir(_) => (0, 0),
+                    })
+                    .collect();
+                progress.add_totals(&sizes);
+
+                let mut failed: Vec<PathBuf> = Vec::new();
+                let mut changes: Vec<Change> = Vec::new();
+                let mut done = (0, 0);
+
+                for (step, size) in steps.iter().zip(sizes) {
+                    if progress.is_cancelled() {
+                        break;
+                    }
+                    match FileOps::execute_step(&step.action, mode, progress) {
+                        Ok(replaced) => {
+                            if let PasteAction::Paste { src, dest, .. } = &step.action {
+                                if let Some(trashed) = replaced {
+                                    changes.push(Change::Trashed {
+                                        original: dest.clone(),
+                                        trashed,
+                                    });
+                                }
+                                changes.push(match mode {
+                                    PasteMode::Copy => Change::Copied {
+                                        src: src.clone(),
+                                        dest: dest.clone(),
+                                    },
+                                    PasteMode::Move => Change::Moved {
+                                        from: src.clone(),
+                                        to: dest.clone(),
+                                    },
+                                });
+                            }
+                        }
+                        Err(error) => {
+                            if error.kind() != ErrorKind::Interrupted {
+                                report.errors.push((step.origin.clone(), error));
+                            }
+                            failed.push(step.origin.clone());
+                        }
+                    }
+                    done = (done.0 + size.0, done.1 + size.1);
+                    progress.set_done(done);
+                }
+
+                // copied entries stay in the buffer, moved directories that couldn't be emptied
+                // aren't done yet
+                report.completed = steps
+                    .into_iter()
+                    .map(|step| step.origin)
+                    .unique()
+                    .filter(|origin| !failed.contains(origin))
+                    .filter(|origin| mode == PasteMode::Move && origin.symlink_metadata().is_err())
+                    .collect();
+
+                Jobs::record(journal, changes);
+            }
+            JobKind::Trash(entries) => {
+                // trashing is a rename, so only entries are counted
+                progress.add_totals(&vec![(1, 0); entries.len()]);
+                let mut changes: Vec<Change> = Vec::new();
+
+                for entry in entries {
+                    if progress.is_cancelled() {
+                        break;
+                    }
+                    match Trash::trash_entry(entry.clone()) {
+                        Ok(trashed) => {
+                            changes.push(Change::Trashed {
+                                original: entry.clone(),
+                                trashed,
+                            });
+                            report.completed.push(entry);
+                        }
+                        Err(error) => report.errors.push((entry, error)),
+                    }
+                    progress.entries_done.fetch_add(1, Ordering::Relaxed);
+                }
+
+                Jobs::record(journal, changes);
+            }
+            JobKind::Delete(entries) => {
+                let sizes: Vec<(u64, u64)> = entries
+                    .iter()
+                    .map(|entry| FileOps::measure(entry))
+                    .collect();
+                progress.add_totals(&sizes);
+
+                for entry in entries {
+                    if progress.is_cancelled() {
+                        break;
+                    }
+                    match FileOps::delete_recursive(&entry, progress) {
+                        Ok(_) => report.completed.push(entry),
+                        Err(error) if error.kind() == ErrorKind::Interrupted => (),
+                        Err(error) => report.errors.push((entry, error)),
+                    }
+                }
+            }
+            JobKind::EmptyTrash(entries) => {
+                let sizes: Vec<(u64, u64)> = entries
+                    .iter()
+                    .map(|entry| FileOps::measure(entry))
+                    .collect();
+                progress.add_totals(&sizes);
+
+                for entry in entries {
+                    if progress.is_cancelled() {
+                        break;
+                    }
+                    match Trash::get_entry(&entry)
+                        .and_then(|trashed| Trash::delete_entry(&trashed, progress))
+                    {
+                        Ok(_) => report.completed.push(entry),
+                        Err(error) if error.kind() == ErrorKind::Interrupted => (),
+                        Err(error) => report.errors.push((entry, error)),
+                    }
+                }
+            }
+            JobKind::Undo => {
+                // the lock isn't held while undoing, other changes can be recorded meanwhile
+                let changes = Jobs::pop_changes(journal, Journal::pop_undo);
+                progress.add_totals(&vec![(1, 0); changes.len()]);
+                let mut undone: Vec<Change> = Vec::new();
+                let mut failed: Vec<Change> = Vec::new();
+
+                // later changes may depend on earlier ones, so they are reverted first
+                for change in changes.into_iter().rev() {
+                    let path = change.get_path();
+                    match Journal::undo_change(change.clone(), progress) {
+                        Ok(change) => undone.insert(0, change),
+                        Err(error) => {
+                            report.errors.push((path, error));
+                            failed.insert(0, change);
+                        }
+                    }
+                    progress.entries_done.fetch_add(1, Ordering::Relaxed);
+                }
+
+                // what failed can be undone again once the problem is fixed
+                if let Ok(mut journal) = journal.lock() {
+                    journal.push_redo(undone);
+                    journal.push_undo(failed);
+                }
+            }
+            JobKind::Redo => {
+                let changes = Jobs::pop_changes(journal, Journal::pop_redo);
+                progress.add_totals(&vec![(1, 0); changes.len()]);
+                let mut redone: Vec<Change> = Vec::new();
+                let mut failed: Vec<Change> = Vec::new();
+
+                for change in changes {
+                    let path = change.get_path();
+                    match Journal::redo_change(change.clone(), progress) {
+                        Ok(change) => redone.push(change),
+                        Err(error) => {
+                            report.errors.push((path, error));
+                            failed.push(change);
+                        }
+                    }
+                    progress.entries_done.fetch_add(1, Ordering::Relaxed);
+                }
+
+                if let Ok(mut journal) = journal.lock() {
+                    journal.push_undo(redone);
+                    journal.push_redo(failed);
+                }
+            }
+        }
+
+        report.cancelled = progress.is_cancelled();
+        report
+    }
+
+    /// takes the changes of an operation out of the journal with [pop](fn), none if it's empty
+    fn pop_changes(
+        journal: &Mutex<Journal>,
+        pop: fn(&mut Journal) -> Option<Vec<Change>>,
+    ) -> Vec<Change> {
+        journal
+            .lock()
+            .ok()
+            .and_then(|mut journal| pop(&mut journal))
+            .unwrap_or_default()
+    }
+
+    fn record(journal: &Mutex<Journal>, changes: Vec<Change>) {
+        if let Ok(mut journal) = journal.lock() {
+            journal.record(changes);
+        }
+    }
+}
+
+impl JobReport {
+    /// lists the errors of the job, one entry per line
+    pub fn get_errors_to_display(&self) -> String {
+        self.errors
+            .iter()
+            .map(|(entry, error)| Dir::get_entry_name(entry.clone()) + ": " + &error.to_string())
+            .join("\n")
+    }
+}
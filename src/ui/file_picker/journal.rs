@@ -0,0 +1,393 @@
+//! Keeps track of the changes made to the file system so they can be undone and redone
+//!
+//! Every job that changes the file system records what it did as a list of [Change]s. Undoing
+//! reverts each change of the last list in reverse order, and the reverted changes become the
+//! ones to redo. Changes that fail to be reverted stay in the undo stack so they can be retried.
+//!
+//! # Examples
+//!
+//! ```rust
+//! let mut journal = Journal::default();
+//!
+//! journal.record(vec![Change::Moved { from, to }]);
+//!
+//! if let Some(changes) = journal.pop_undo() {
+//!     let mut undone = Vec::new();
+//!     let mut failed = Vec::new();
+//!     for change in changes.into_iter().rev() {
+//!         match Journal::undo_change(change.clone(), &progress) {
+//!             Ok(change) => undone.insert(0, change),
+//!             Err(_) => failed.insert(0, change),
+//!         }
+//!     }
+//!     journal.push_redo(undone);
+//!     journal.push_undo(failed);
+//! }
+//! ```
+
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+
+use super::{
+    file_ops::{FileOps, Progress},
+    trash::Trash,
+};
+
+/// a single reversible change to the file system
+#[derive(Clone)]
+pub enum Change {
+    Copied {
+        src: PathBuf,
+        dest: PathBuf,
+    },
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Trashed {
+        original: PathBuf,
+        trashed: PathBuf,
+    },
+    /// [top](PathBuf) is the first directory created on the way to [path](PathBuf), or the path
+    /// itself if its parent already existed
+    Created {
+        top: PathBuf,
+        path: PathBuf,
+        is_dir: bool,
+    },
+}
+
+/// [undo_stack](Vec<Vec<Change>>): changes done by each operation, the last one is undone first
+/// [redo_stack](Vec<Vec<Change>>): changes undone, the last one is redone first
+#[derive(Default)]
+pub struct Journal {
+    undo_stack: Vec<Vec<Change>>,
+    redo_stack: Vec<Vec<Change>>,
+}
+
+impl Journal {
+    /// records the changes of a new operation, which makes whatever was undone not redoable
+    pub fn record(&mut self, changes: Vec<Change>) {
+        if !changes.is_empty() {
+            self.undo_stack.push(changes);
+            self.redo_stack.clear();
+        }
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Vec<Change>> {
+        self.undo_stack.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Vec<Change>> {
+        self.redo_stack.pop()
+    }
+
+    /// stores changes that can be redone, like the ones just undone or the ones that failed to be
+    /// redone
+    pub fn push_redo(&mut self, changes: Vec<Change>) {
+        if !changes.is_empty() {
+            self.redo_stack.push(changes);
+        }
+    }
+
+    /// stores changes that can be undone, like the ones just redone or the ones that failed to be
+    /// undone
+    pub fn push_undo(&mut self, changes: Vec<Change>) {
+        if !changes.is_empty() {
+            self.undo_stack.push(changes);
+        }
+    }
+
+    /// reverts [change](Change), returning it as it has to be redone
+    pub fn undo_change(change: Change, progress: &Progress) -> Result<Change> {
+        match change {
+            // the copy may have been changed since, so it goes to the trash instead of being
+            // deleted. the original is still there to copy it again
+            Change::Copied { src, dest } => {
+                Trash::trash_entry(dest.clone())?;
+                Ok(Change::Copied { src, dest })
+            }
+            Change::Moved { from, to } => {
+                Journal::move_back(&to, &from, progress)?;
+                Ok(Change::Moved { from, to })
+            }
+            Change::Trashed { original, trashed } => {
+                Journal::check_free(&original)?;
+                Trash::restore_entry(&Trash::get_entry(&trashed)?, &original)?;
+                Ok(Change::Trashed { original, trashed })
+            }
+            // only removes what is still empty, so nothing added since then is lost
+            Change::Created { top, path, is_dir } => {
+                match is_dir {
+                    true => fs::remove_dir(&path)?,
+                    false => match path.symlink_metadata()?.len() {
+                        0 => fs::remove_file(&path)?,
+                        _ => {
+                            return Err(Error::new(
+                                ErrorKind::DirectoryNotEmpty,
+                                "file isn't empty anymore",
+                            ))
+                        }
+                    },
+                }
+                for dir in path
+                    .ancestors()
+                    .skip(1)
+                    .take_while(|dir| dir.starts_with(&top))
+                {
+                    fs::remove_dir(dir)?;
+                }
+                Ok(Change::Created { top, path, is_dir })
+            }
+        }
+    }
+
+    /// applies [change](Change) again, returning it as it has to be undone
+    pub fn redo_change(change: Change, progress: &Progress) -> Result<Change> {
+        match change {
+            Change::Copied { src, dest } => {
+                Journal::check_free(&dest)?;
+                FileOps::copy_recursive(&src, &dest, progress)?;
+                Ok(Change::Copied { src, dest })
+            }
+            Change::Moved { from, to } => {
+                Journal::move_back(&from, &to, progress)?;
+                Ok(Change::Moved { from, to })
+            }
+            // trashing again puts the entry in a new place inside the trash
+            Change::Trashed { original, .. } => {
+                let trashed = Trash::trash_entry(original.clone())?;
+                Ok(Change::Trashed { original, trashed })
+            }
+            Change::Created { top, path, is_dir } => {
+                Journal::check_free(&top)?;
+                FileOps::create_entry(&path, is_dir)?;
+                Ok(Change::Created { top, path, is_dir })
+            }
+        }
+    }
+
+    /// moves [src](Path) to [dest](Path), recreating the directories it was in if needed
+    fn move_back(src: &Path, dest: &Path, progress: &Progress) -> Result<()> {
+        Journal::check_free(dest)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        FileOps::move_recursive(src, dest, progress)
+    }
+
+    /// errors if something was created at [path](Path) since the change was made
+    fn check_free(path: &Path) -> Result<()> {
+        match path.symlink_metadata() {
+            Ok(_) => Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            )),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl Change {
+    /// the entry the change is about, for error messages
+    pub fn get_path(&self) -> PathBuf {
+        match self {
+            Change::Copied { dest, .. } => dest.clone(),
+            Change::Moved { to, .. } => to.clone(),
+            Change::Trashed { original, .. } => original.clone(),
+            Change::Created { path, .. } => path.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    /// undoes the last operation of [journal](Journal), like the undo job does
+    fn undo(journal: &mut Journal) -> Result<()> {
+        let changes = journal.pop_undo().expect("nothing to undo");
+        let mut undone = Vec::new();
+        for change in changes.into_iter().rev() {
+            undone.insert(0, Journal::undo_change(change, &Progress::default())?);
+        }
+        journal.push_redo(undone);
+        Ok(())
+    }
+
+    /// redoes the last undone operation of [journal](Journal), like the redo job does
+    fn redo(journal: &mut Journal) -> Result<()> {
+        let changes = journal.pop_redo().expect("nothing to redo");
+        let mut redone = Vec::new();
+        for change in changes {
+            redone.push(Journal::redo_change(change, &Progress::default())?);
+        }
+        journal.push_undo(redone);
+        Ok(())
+    }
+
+    #[test]
+    fn moves_are_undone_and_redone() {
+        let dir = TestDir::new("journal-move");
+        let from = dir.write("a/notes.txt", "notes");
+        let to = dir.path.join("b/notes.txt");
+        fs::create_dir(dir.path.join("b")).unwrap();
+        fs::rename(&from, &to).unwrap();
+        // the directory it was moved from is gone too
+        fs::remove_dir(dir.path.join("a")).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![Change::Moved {
+            from: from.clone(),
+            to: to.clone(),
+        }]);
+
+        undo(&mut journal).unwrap();
+        assert_eq!(fs::read_to_string(&from).unwrap(), "notes");
+        assert!(to.symlink_metadata().is_err());
+
+        redo(&mut journal).unwrap();
+        assert_eq!(fs::read_to_string(&to).unwrap(), "notes");
+        assert!(from.symlink_metadata().is_err());
+
+        assert!(journal.pop_redo().is_none());
+        assert!(journal.pop_undo().is_some());
+    }
+
+    #[test]
+    fn undone_copies_go_to_the_trash() {
+        let dir = TestDir::new("journal-copy");
+        let src = dir.write("src.txt", "contents");
+        let dest = dir.path.join("dest.txt");
+        FileOps::copy_recursive(&src, &dest, &Progress::default()).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![Change::Copied {
+            src: src.clone(),
+            dest: dest.clone(),
+        }]);
+
+        undo(&mut journal).unwrap();
+        assert!(dest.symlink_metadata().is_err());
+        assert!(src.exists());
+        let trashed = Trash::list_entries()
+            .into_iter()
+            .find(|entry| entry.original_path == dest)
+            .expect("the copy isn't in the trash");
+
+        redo(&mut journal).unwrap();
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "contents");
+
+        Trash::delete_entry(&trashed, &Progress::default()).unwrap();
+    }
+
+    #[test]
+    fn trashed_entries_are_restored_and_trashed_again() {
+        let dir = TestDir::new("journal-trash");
+        let original = dir.write("notes.txt", "notes");
+        let trashed = Trash::trash_entry(original.clone()).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![Change::Trashed {
+            original: original.clone(),
+            trashed,
+        }]);
+
+        undo(&mut journal).unwrap();
+        assert_eq!(fs::read_to_string(&original).unwrap(), "notes");
+
+        redo(&mut journal).unwrap();
+        assert!(original.symlink_metadata().is_err());
+
+        // the entry is somewhere else in the trash now, which undoing again has to know
+        undo(&mut journal).unwrap();
+        assert_eq!(fs::read_to_string(&original).unwrap(), "notes");
+    }
+
+    #[test]
+    fn created_entries_are_removed_with_their_new_parents() {
+        let dir = TestDir::new("journal-create");
+        let top = dir.path.join("a");
+        let path = dir.path.join("a/b/notes.txt");
+        FileOps::create_entry(&path, false).unwrap();
+
+        let mut journal = Journal::default();
+        journal.record(vec![Change::Created {
+            top: top.clone(),
+            path: path.clone(),
+            is_dir: false,
+        }]);
+
+        undo(&mut journal).unwrap();
+        assert!(top.symlink_metadata().is_err());
+        assert!(dir.path.exists());
+
+        redo(&mut journal).unwrap();
+        assert!(path.is_file());
+    }
+
+    #[test]
+    fn undo_keeps_what_changed_since() {
+        let dir = TestDir::new("journal-create-changed");
+        let path = dir.path.join("notes.txt");
+        FileOps::create_entry(&path, false).unwrap();
+        fs::write(&path, "written since").unwrap();
+
+        let change = Change::Created {
+            top: path.clone(),
+            path: path.clone(),
+            is_dir: false,
+        };
+        assert!(Journal::undo_change(change, &Progress::default()).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "written since");
+    }
+
+    #[test]
+    fn redo_never_replaces_an_entry() {
+        let dir = TestDir::new("journal-redo-taken");
+        let from = dir.write("a.txt", "moved");
+        let to = dir.path.join("b.txt");
+
+        let mut journal = Journal::default();
+        journal.record(vec![Change::Moved {
+            from: from.clone(),
+            to: to.clone(),
+        }]);
+        fs::rename(&from, &to).unwrap();
+        undo(&mut journal).unwrap();
+        dir.write("b.txt", "new");
+
+        assert_eq!(
+            redo(&mut journal).unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+        assert_eq!(fs::read_to_string(&from).unwrap(), "moved");
+        assert_eq!(fs::read_to_string(&to).unwrap(), "new");
+    }
+
+    #[test]
+    fn recording_drops_what_could_be_redone() {
+        let mut journal = Journal::default();
+        journal.push_redo(vec![Change::Moved {
+            from: PathBuf::from("a"),
+            to: PathBuf::from("b"),
+        }]);
+
+        journal.record(Vec::new());
+        assert!(journal.pop_redo().is_some());
+
+        journal.push_redo(vec![Change::Moved {
+            from: PathBuf::from("a"),
+            to: PathBuf::from("b"),
+        }]);
+        journal.record(vec![Change::Moved {
+            from: PathBuf::from("c"),
+            to: PathBuf::from("d"),
+        }]);
+        assert!(journal.pop_redo().is_none());
+    }
+}
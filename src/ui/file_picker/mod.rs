@@ -1,2 +1,13 @@
+pub mod bookmarks;
+pub mod bulk_rename;
 pub mod dir;
+pub mod file_ops;
+#[allow(clippy::module_inception)]
 pub mod file_picker;
+pub mod frecency;
+pub mod history;
+pub mod jobs;
+pub mod journal;
+#[cfg(test)]
+pub mod test_utils;
+pub mod trash;
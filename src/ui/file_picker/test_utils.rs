@@ -0,0 +1,45 @@
+//! Helpers for the tests of the file operations
+//!
+//! Every test works inside its own directory in the temp directory, so tests running in parallel
+//! never see each other's entries.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// [path](PathBuf): the directory of the test, removed with everything in it once dropped
+pub struct TestDir {
+    pub path: PathBuf,
+}
+
+impl TestDir {
+    /// creates an empty directory for the test [name](str), which has to be unique as tests run
+    /// in parallel
+    pub fn new(name: &str) -> TestDir {
+        let path = env::temp_dir().join(format!("navfs-test-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        // the trash stores absolute paths without symlinks in them
+        TestDir {
+            path: path.canonicalize().unwrap(),
+        }
+    }
+
+    /// writes [contents](str) to the file at [relative](Path), creating the directories it's in
+    pub fn write(&self, relative: impl AsRef<Path>, contents: &str) -> PathBuf {
+        let path = self.path.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,571 @@
+//! Moves entries to the trash following the freedesktop.org Trash specification
+//!
+//! Entries in the same file system as the home directory go to `$XDG_DATA_HOME/Trash`, entries in
+//! other mounts go to `$topdir/.Trash/$uid` (if the administrator created a sticky `.Trash`) or
+//! `$topdir/.Trash-$uid`. Each trashed entry gets a `.trashinfo` file in the `info` directory
+//! recording where it came from and when it was deleted, so it can be restored later.
+//!
+//! Trashed entries are listed from the home trash and the trash directories of every mount point
+//! as [TrashedEntry]s, which can be restored to their original location.
+//!
+//! See <https://specifications.freedesktop.org/trash-spec/latest/>
+//!
+//! # Examples
+//!
+//! ```rust
+//! let trashed: PathBuf = Trash::trash_entry(PathBuf::from("text.txt"))?;
+//!
+//! for entry in Trash::list_entries() {
+//!     if entry.original_path.ends_with("text.txt") {
+//!         Trash::restore_entry(&entry, &entry.original_path)?;
+//!     }
+//! }
+//! ```
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, DirBuilder, OpenOptions},
+    io::{Error, ErrorKind, Result, Write},
+    os::unix::{
+        ffi::OsStringExt,
+        fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use chrono::{Local, NaiveDateTime};
+use users::get_current_uid;
+
+use crate::ui::utils::Utils;
+
+use super::file_ops::{FileOps, Progress};
+
+/// format of the `DeletionDate` key in `.trashinfo` files
+pub const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// sticky bit, required on `$topdir/.Trash` for it to be trusted
+const STICKY_BIT: u32 = 0o1000;
+
+/// an entry in one of the trash directories
+///
+/// [trashed_path](PathBuf): where the entry currently is, inside the `files` directory
+/// [info_path](PathBuf): its `.trashinfo` file
+/// [original_path](PathBuf): absolute path the entry was trashed from
+/// [deletion_date](String): when the entry was trashed, as written in the `.trashinfo`
+pub struct TrashedEntry {
+    pub trashed_path: PathBuf,
+    pub info_path: PathBuf,
+    pub original_path: PathBuf,
+    pub deletion_date: String,
+}
+
+pub struct Trash {}
+
+impl Trash {
+    /// moves [path](PathBuf) to the trash directory of the file system it is in
+    /// returns the path of the entry inside the trash
+    pub fn trash_entry(path: PathBuf) -> Result<PathBuf> {
+        let path = Trash::get_absolute_path(&path)?;
+        let metadata = path.symlink_metadata()?;
+        let name = path.file_name().ok_or(Error::new(
+            ErrorKind::InvalidInput,
+            format!("can't trash {}", path.display()),
+        ))?;
+
+        let (trash_dir, topdir) = Trash::get_trash_dir_for(&path, metadata.dev())?;
+
+        let files_dir = trash_dir.join("files");
+        let info_dir = trash_dir.join("info");
+        for dir in [&files_dir, &info_dir] {
+            DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        }
+
+        // trash directories outside of home store paths relative to their mount point
+        let original_path = match topdir {
+            Some(topdir) => path.strip_prefix(topdir).unwrap_or(&path).to_path_buf(),
+            None => path.clone(),
+        };
+
+        let info = "[Trash Info]\nPath=".to_string()
+            + &Trash::encode_path(&original_path)
+            + "\nDeletionDate="
+            + &Local::now().format(DELETION_DATE_FORMAT).to_string()
+            + "\n";
+
+        // the info file is created exclusively to reserve the name, as the spec requires
+        let name = name.to_string_lossy().to_string();
+        let mut suffix = 1;
+        let (trashed_name, mut info_file) = loop {
+            let candidate = match suffix {
+                1 => name.clone(),
+                n => name.clone() + "." + &n.to_string(),
+            };
+
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(info_dir.join(candidate.clone() + ".trashinfo"))
+            {
+                Ok(file) if files_dir.join(&candidate).symlink_metadata().is_err() => {
+                    break (candidate, file)
+                }
+                Ok(_) => {
+                    // an entry without info file, the name is still taken
+                    let _ = fs::remove_file(info_dir.join(candidate + ".trashinfo"));
+                }
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => (),
+                Err(error) => return Err(error),
+            }
+            suffix += 1;
+        };
+
+        let info_path = info_dir.join(trashed_name.clone() + ".trashinfo");
+        let trashed_path = files_dir.join(trashed_name);
+
+        if let Err(error) = info_file
+            .write_all(info.as_bytes())
+            .and_then(|_| fs::rename(&path, &trashed_path))
+        {
+            let _ = fs::remove_file(info_path);
+            return Err(error);
+        }
+
+        Ok(trashed_path)
+    }
+
+    /// lists the entries of every trash directory the user has, most recently trashed first
+    /// entries without a valid `.trashinfo` file are ignored
+    pub fn list_entries() -> Vec<TrashedEntry> {
+        let mut entries: Vec<TrashedEntry> = Vec::new();
+
+        for (trash_dir, topdir) in Trash::get_trash_dirs() {
+            let Ok(infos) = fs::read_dir(trash_dir.join("info")) else {
+                continue;
+            };
+
+            for info in infos.filter_map(|info| info.ok()) {
+                let info_path = info.path();
+                if info_path.extension().unwrap_or_default() != "trashinfo" {
+                    continue;
+                }
+
+                let trashed_path = trash_dir
+                    .join("files")
+                    .join(info_path.file_stem().unwrap_or_default());
+
+                if let Some((original_path, deletion_date)) =
+                    Trash::read_info(&info_path, topdir.as_deref())
+                {
+                    entries.push(TrashedEntry {
+                        trashed_path,
+                        info_path,
+                        original_path,
+                        deletion_date,
+                    });
+                }
+            }
+        }
+
+        // the date format sorts chronologically as text
+        entries.sort_by(|a, b| b.deletion_date.cmp(&a.deletion_date));
+        entries
+    }
+
+    /// returns the trashed entry at [trashed_path](Path), as returned by [Trash::trash_entry]
+    pub fn get_entry(trashed_path: &Path) -> Result<TrashedEntry> {
+        let not_found = || {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("{} is not in the trash", trashed_path.display()),
+            )
+        };
+
+        let name = trashed_path.file_name().ok_or_else(not_found)?;
+        let trash_dir = trashed_path
+            .parent()
+            .and_then(|files_dir| files_dir.parent())
+            .ok_or_else(not_found)?;
+
+        let (_, topdir) = Trash::get_trash_dirs()
+            .into_iter()
+            .find(|(dir, _)| dir == trash_dir)
+            .ok_or_else(not_found)?;
+
+        let info_path = trash_dir
+            .join("info")
+            .join(name.to_string_lossy().to_string() + ".trashinfo");
+
+        let (original_path, deletion_date) =
+            Trash::read_info(&info_path, topdir.as_deref()).ok_or_else(not_found)?;
+
+        Ok(TrashedEntry {
+            trashed_path: trashed_path.to_path_buf(),
+            info_path,
+            original_path,
+            deletion_date,
+        })
+    }
+
+    /// moves a trashed entry back to [dest](Path), usually its original path, creating the
+    /// missing parent directories. errors if [dest](Path) is taken
+    pub fn restore_entry(entry: &TrashedEntry, dest: &Path) -> Result<()> {
+        if dest.symlink_metadata().is_ok() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} already exists", dest.display()),
+            ));
+        }
+
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        FileOps::move_recursive(&entry.trashed_path, dest, &Progress::default())?;
+        fs::remove_file(&entry.info_path)
+    }
+
+    /// permanently deletes a trashed entry, and its `.trashinfo` file only once the entry is gone,
+    /// so an entry that fails to be deleted still shows in the trash
+    pub fn delete_entry(entry: &TrashedEntry, progress: &Progress) -> Result<()> {
+        FileOps::delete_recursive(&entry.trashed_path, progress)?;
+        fs::remove_file(&entry.info_path)
+    }
+
+    /// returns the deletion date of [entry](TrashedEntry) in the same format used for the
+    /// modification date of entries
+    pub fn get_deletion_date_to_display(entry: &TrashedEntry) -> String {
+        match NaiveDateTime::parse_from_str(&entry.deletion_date, DELETION_DATE_FORMAT) {
+            Ok(date) => date.format("%d %b %Y %H:%M").to_string(),
+            Err(_) => entry.deletion_date.clone(),
+        }
+    }
+
+    /// reads the original path and deletion date from a `.trashinfo` file
+    fn read_info(info_path: &Path, topdir: Option<&Path>) -> Option<(PathBuf, String)> {
+        let content = fs::read_to_string(info_path).ok()?;
+        let mut lines = content.lines();
+
+        if lines.next()?.trim() != "[Trash Info]" {
+            return None;
+        }
+
+        let mut original_path: Option<PathBuf> = None;
+        let mut deletion_date = "".to_string();
+
+        for line in lines {
+            if let Some(path) = line.strip_prefix("Path=") {
+                let path = Trash::decode_path(path);
+                // relative paths are relative to the mount point the trash is in
+                original_path = Some(match topdir {
+                    Some(topdir) if path.is_relative() => topdir.join(path),
+                    _ => path,
+                });
+            } else if let Some(date) = line.strip_prefix("DeletionDate=") {
+                deletion_date = date.to_string();
+            }
+        }
+
+        Some((original_path?, deletion_date))
+    }
+
+    /// returns every existing trash directory paired with the mount point it belongs to
+    /// (`None` for the home trash)
+    fn get_trash_dirs() -> Vec<(PathBuf, Option<PathBuf>)> {
+        let mut trash_dirs: Vec<(PathBuf, Option<PathBuf>)> = vec![(Trash::get_home_trash(), None)];
+        let uid = get_current_uid().to_string();
+
+        // mount points are the second field, with spaces and such escaped as octal
+        let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+        for mount in mounts.lines().filter_map(|line| line.split(' ').nth(1)) {
+            let topdir = Trash::decode_mount_point(mount);
+            for trash_dir in [
+                topdir.join(".Trash").join(&uid),
+                topdir.join(".Trash-".to_string() + &uid),
+            ] {
+                let is_listed = trash_dirs.iter().any(|(listed, _)| *listed == trash_dir);
+                if trash_dir.is_dir() && !is_listed {
+                    trash_dirs.push((trash_dir, Some(topdir.clone())));
+                }
+            }
+        }
+
+        trash_dirs
+    }
+
+    /// undoes the `\ooo` octal escapes used in `/proc/self/mounts`
+    fn decode_mount_point(mount: &str) -> PathBuf {
+        let bytes = mount.as_bytes();
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let escaped = bytes
+                .get(i + 1..i + 4)
+                .filter(|_| bytes[i] == b'\\')
+                .and_then(|octal| u8::from_str_radix(std::str::from_utf8(octal).ok()?, 8).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 4;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        PathBuf::from(OsString::from_vec(decoded))
+    }
+
+    /// returns `$XDG_DATA_HOME/Trash`, defaulting to `~/.local/share/Trash`
+    pub fn get_home_trash() -> PathBuf {
+        Utils::get_data_home().join("Trash")
+    }
+
+    /// returns the trash directory to be used for an entry in the device [dev](u64) and, if it's
+    /// not the home trash, the top directory of the mount the trash is in
+    fn get_trash_dir_for(path: &Path, dev: u64) -> Result<(PathBuf, Option<PathBuf>)> {
+        let home_trash = Trash::get_home_trash();
+
+        // the home trash may not exist yet, so it checks the closest ancestor that does
+        let home_trash_dev = home_trash
+            .ancestors()
+            .find_map(|ancestor| ancestor.metadata().ok())
+            .map(|metadata| metadata.dev());
+
+        if home_trash_dev == Some(dev) {
+            return Ok((home_trash, None));
+        }
+
+        let topdir = Trash::get_mount_point(path, dev);
+        let uid = get_current_uid().to_string();
+
+        // $topdir/.Trash is only used if it is a real directory with the sticky bit set
+        let admin_trash = topdir.join(".Trash");
+        if let Ok(metadata) = admin_trash.symlink_metadata() {
+            if metadata.is_dir() && metadata.permissions().mode() & STICKY_BIT != 0 {
+                let user_trash = admin_trash.join(&uid);
+                if DirBuilder::new()
+                    .recursive(true)
+                    .mode(0o700)
+                    .create(&user_trash)
+                    .is_ok()
+                {
+                    return Ok((user_trash, Some(topdir)));
+                }
+            }
+        }
+
+        Ok((topdir.join(".Trash-".to_string() + &uid), Some(topdir)))
+    }
+
+    /// returns the top most ancestor of [path](Path) that is still in the device [dev](u64)
+    fn get_mount_point(path: &Path, dev: u64) -> PathBuf {
+        path.ancestors()
+            .skip(1)
+            .take_while(|ancestor| {
+                ancestor
+                    .metadata()
+                    .map(|metadata| metadata.dev() == dev)
+                    .unwrap_or(false)
+            })
+            .last()
+            .unwrap_or(path)
+            .to_path_buf()
+    }
+
+    /// makes [path](Path) absolute resolving its parents but not the entry itself, as trashing a
+    /// symlink must move the link and not what it points to
+    fn get_absolute_path(path: &Path) -> Result<PathBuf> {
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+                Ok(parent.canonicalize()?.join(name))
+            }
+            (_, Some(name)) => Ok(env::current_dir()?.join(name)),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("can't trash {}", path.display()),
+            )),
+        }
+    }
+
+    /// undoes [Trash::encode_path], invalid escapes are kept as they are
+    pub fn decode_path(path: &str) -> PathBuf {
+        let bytes = path.as_bytes();
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let escaped = bytes
+                .get(i + 1..i + 3)
+                .filter(|_| bytes[i] == b'%')
+                .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+            match escaped {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        PathBuf::from(OsString::from_vec(decoded))
+    }
+
+    /// percent encodes a path as required for the `Path` key, keeping `/` and unreserved chars
+    pub fn encode_path(path: &Path) -> String {
+        path.as_os_str()
+            .as_encoded_bytes()
+            .iter()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                    (*byte as char).to_string()
+                }
+                _ => format!("%{byte:02X}"),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    #[test]
+    fn trashing_moves_the_entry_and_writes_its_info() {
+        let dir = TestDir::new("trash-entry");
+        let file = dir.write("docs/notes.txt", "notes");
+
+        let trashed = Trash::trash_entry(file.clone()).unwrap();
+
+        assert!(file.symlink_metadata().is_err());
+        assert!(trashed.starts_with(Trash::get_home_trash().join("files")));
+        assert_eq!(fs::read_to_string(&trashed).unwrap(), "notes");
+
+        let entry = Trash::get_entry(&trashed).unwrap();
+        let info = fs::read_to_string(&entry.info_path).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath="));
+        assert!(info.contains("\nDeletionDate="));
+        assert_eq!(entry.original_path, file);
+    }
+
+    #[test]
+    fn entries_with_the_same_name_get_their_own_place() {
+        let dir = TestDir::new("trash-same-name");
+        let first = Trash::trash_entry(dir.write("one/same-name.txt", "1")).unwrap();
+        let second = Trash::trash_entry(dir.write("two/same-name.txt", "2")).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "1");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "2");
+        assert_eq!(
+            Trash::get_entry(&second).unwrap().original_path,
+            dir.path.join("two/same-name.txt")
+        );
+    }
+
+    #[test]
+    fn symlinks_are_trashed_instead_of_their_target() {
+        let dir = TestDir::new("trash-symlink");
+        let target = dir.write("target.txt", "target");
+        let link = dir.path.join("link");
+        symlink(&target, &link).unwrap();
+
+        let trashed = Trash::trash_entry(link.clone()).unwrap();
+
+        assert!(link.symlink_metadata().is_err());
+        assert!(trashed.symlink_metadata().unwrap().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "target");
+    }
+
+    #[test]
+    fn paths_are_encoded_both_ways() {
+        let path = Path::new("/tmp/a b/100%/ñ");
+        let encoded = Trash::encode_path(path);
+
+        assert!(!encoded.contains(' '));
+        assert_eq!(Trash::decode_path(&encoded), path);
+    }
+
+    #[test]
+    fn trashed_entries_can_be_restored() {
+        let dir = TestDir::new("trash-restore");
+        let file = dir.write("docs/notes.txt", "notes");
+        let trashed = Trash::trash_entry(file.clone()).unwrap();
+
+        let entry = Trash::list_entries()
+            .into_iter()
+            .find(|entry| entry.trashed_path == trashed)
+            .unwrap();
+        assert_eq!(entry.original_path, file);
+
+        Trash::restore_entry(&entry, &file).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "notes");
+        assert!(trashed.symlink_metadata().is_err());
+        assert!(!entry.info_path.exists());
+    }
+
+    #[test]
+    fn restoring_never_replaces_an_entry() {
+        let dir = TestDir::new("trash-restore-taken");
+        let file = dir.write("notes.txt", "trashed");
+        let trashed = Trash::trash_entry(file.clone()).unwrap();
+        dir.write("notes.txt", "new");
+
+        let entry = Trash::get_entry(&trashed).unwrap();
+        let error = Trash::restore_entry(&entry, &file).unwrap_err();
+
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&trashed).unwrap(), "trashed");
+        assert!(entry.info_path.exists());
+    }
+
+    #[test]
+    fn restoring_recreates_missing_directories() {
+        let dir = TestDir::new("trash-restore-parents");
+        let file = dir.write("a/b/notes.txt", "notes");
+        let trashed = Trash::trash_entry(file.clone()).unwrap();
+        fs::remove_dir_all(dir.path.join("a")).unwrap();
+
+        Trash::restore_entry(&Trash::get_entry(&trashed).unwrap(), &file).unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "notes");
+    }
+
+    #[test]
+    fn deleting_removes_the_entry_and_its_info() {
+        let dir = TestDir::new("trash-delete");
+        dir.write("docs/a.txt", "a");
+        let trashed = Trash::trash_entry(dir.path.join("docs")).unwrap();
+        let entry = Trash::get_entry(&trashed).unwrap();
+
+        Trash::delete_entry(&entry, &Progress::default()).unwrap();
+
+        assert!(trashed.symlink_metadata().is_err());
+        assert!(!entry.info_path.exists());
+        assert!(Trash::get_entry(&trashed).is_err());
+    }
+
+    #[test]
+    fn failed_deletion_keeps_the_info() {
+        let dir = TestDir::new("trash-delete-cancelled");
+        let trashed = Trash::trash_entry(dir.write("a.txt", "a")).unwrap();
+        let entry = Trash::get_entry(&trashed).unwrap();
+        let progress = Progress::default();
+        progress.cancel();
+
+        assert!(Trash::delete_entry(&entry, &progress).is_err());
+
+        assert!(trashed.exists());
+        assert!(entry.info_path.exists());
+    }
+}
@@ -0,0 +1,374 @@
+//! Full screen fuzzy finder over every entry under a directory
+//!
+//! The tree is walked on a background task that sends the entries found in batches, so the first
+//! results show up right away and the finder stays responsive even on huge trees. Entries are
+//! scored a chunk at a time between key presses and only the best ones are kept.
+//!
+//! # Example
+//! ```rust
+//! if let Some(path) = FuzzyFinder::show(PathBuf::from("/home/user")) {
+//!     // jumps to where the chosen entry is
+//!     file_picker.initialize(path.parent(), Some(Selection::Entry(path)));
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    prelude::*,
+    widgets::{block::Position, Block, List, ListState, Paragraph},
+};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::{
+    theme::Theme,
+    tui,
+    ui::{file_picker::dir::Dir, popup::popup::show_info, text_input::text_input::TextInput},
+};
+
+use super::matcher::Matcher;
+
+/// how many entries are sent at once by the walker
+const BATCH_SIZE: usize = 1024;
+/// how many entries are scored between checks for key presses
+const SCORE_CHUNK_SIZE: usize = 10_000;
+/// only the best results are kept, nobody scrolls past them anyway
+const MAX_RESULTS: usize = 1000;
+
+/// [root](PathBuf): the directory being searched
+/// [candidates](Vec<String>): entries found so far, relative to the root, directories end in `/`
+/// [receiver](UnboundedReceiver): batches of entries sent by the walker
+/// [walking](bool): if the walker is still sending entries
+/// [cancelled](AtomicBool): tells the walker to stop once the finder is closed
+/// [input](TextInput): where the query is typed
+/// [matcher](Matcher): the query being matched
+/// [scored](usize): how many candidates were scored with the current query
+/// [results](Vec<(i64, usize)>): score and index of the best candidates, best first
+/// [index](usize): the index of the result under the cursor
+pub struct FuzzyFinder {
+    root: PathBuf,
+    candidates: Vec<String>,
+    receiver: UnboundedReceiver<Vec<String>>,
+    walking: bool,
+    cancelled: Arc<AtomicBool>,
+    input: TextInput,
+    matcher: Matcher,
+    scored: usize,
+    results: Vec<(i64, usize)>,
+    index: usize,
+}
+
+impl FuzzyFinder {
+    /// shows the finder until the user picks an entry, which is returned, or closes it
+    pub fn show(root: PathBuf) -> Option<PathBuf> {
+        let (sender, receiver) = unbounded_channel::<Vec<String>>();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let walker_root = root.clone();
+        let walker_cancelled = cancelled.clone();
+        tokio::task::spawn_blocking(move || {
+            FuzzyFinder::walk(&walker_root, &walker_cancelled, &mut |batch| {
+                let candidates = batch
+                    .into_iter()
+                    .map(|(relative, is_dir)| {
+                        let relative = relative.to_string_lossy().to_string();
+                        if is_dir {
+                            relative + "/"
+                        } else {
+                            relative
+                        }
+                    })
+                    .collect();
+                sender.send(candidates).is_ok()
+            })
+        });
+
+        let mut finder = FuzzyFinder {
+            root,
+            candidates: Vec::new(),
+            receiver,
+            walking: true,
+            cancelled,
+            input: TextInput::new(""),
+            matcher: Matcher::new(""),
+            scored: 0,
+            results: Vec::new(),
+            index: 0,
+        };
+
+        // try catch
+        let res = finder.run().unwrap_or_else(|error| {
+            show_info("Error displaying fuzzy finder", error.to_string());
+            None
+        });
+
+        finder.cancelled.store(true, Ordering::Relaxed);
+        res
+    }
+
+    fn run(&mut self) -> Result<Option<PathBuf>> {
+        let mut term = tui::init()?;
+        term.clear()?;
+
+        let mut needs_draw = true;
+
+        loop {
+            if needs_draw {
+                term.draw(|frame| self.render(frame.size(), frame.buffer_mut()))?;
+            }
+
+            needs_draw = false;
+            // waits less while there is work to do, so results keep coming in
+            let timeout = match self.walking || self.scored < self.candidates.len() {
+                true => Duration::from_millis(1),
+                false => Duration::from_millis(100),
+            };
+
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        match key.code {
+                            KeyCode::Esc => return Ok(None),
+                            KeyCode::Enter => return Ok(self.get_selected()),
+                            _ => self.handle_keys(key),
+                        }
+                        needs_draw = true;
+                    }
+                }
+            }
+
+            needs_draw |= self.receive_candidates();
+            needs_draw |= self.score_chunk();
+        }
+    }
+
+    fn handle_keys(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Down => self.select_next(),
+            KeyCode::Up => self.select_prev(),
+            KeyCode::Char('n') | KeyCode::Char('j') if ctrl => self.select_next(),
+            KeyCode::Char('p') | KeyCode::Char('k') if ctrl => self.select_prev(),
+            _ => {
+                if self.input.handle_keys(key) {
+                    // everything is scored again with the new query
+                    self.matcher = Matcher::new(&self.input.get_value());
+                    self.scored = 0;
+                    self.results.clear();
+                    self.index = 0;
+                }
+            }
+        }
+    }
+
+    fn select_next(&mut self) {
+        self.index = (self.index + 1).min(self.results.len().max(1) - 1);
+    }
+
+    fn select_prev(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    fn get_selected(&self) -> Option<PathBuf> {
+        let (_, candidate) = self.results.get(self.index)?;
+        Some(
+            self.root
+                .join(self.candidates[*candidate].trim_end_matches('/')),
+        )
+    }
+
+    /// takes the batches sent by the walker, returns `true` if there was any
+    fn receive_candidates(&mut self) -> bool {
+        let mut received = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(batch) => {
+                    self.candidates.extend(batch);
+                    received = true;
+                }
+                Err(tokio::sync::mpsc::error::TryRecvError::Empty) => break,
+                Err(tokio::sync::mpsc::error::TryRecvError::Disconnected) => {
+                    received |= self.walking;
+                    self.walking = false;
+                    break;
+                }
+            }
+        }
+        received
+    }
+
+    /// scores the next chunk of candidates, returns `true` if any was scored
+    fn score_chunk(&mut self) -> bool {
+        let end = (self.scored + SCORE_CHUNK_SIZE).min(self.candidates.len());
+        if self.scored == end {
+            return false;
+        }
+
+        for index in self.scored..end {
+            let Some((score, _)) = self.matcher.score(&self.candidates[index]) else {
+                continue;
+            };
+
+            // ties go to shorter paths, then to the ones found first
+            let key =
+                |(score, index): &(i64, usize)| (-score, self.candidates[*index].len(), *index);
+            let new = (score, index);
+            if self.results.len() == MAX_RESULTS
+                && self
+                    .results
+                    .last()
+                    .is_some_and(|last| key(&new) >= key(last))
+            {
+                continue;
+            }
+
+            let position = self
+                .results
+                .partition_point(|result| key(result) < key(&new));
+            self.results.insert(position, new);
+            self.results.truncate(MAX_RESULTS);
+        }
+
+        self.scored = end;
+        true
+    }
+
+    /// walks the tree under [root](Path) breadth first, so shallow entries come first, calling
+    /// [send](FnMut) with batches of paths relative to the root, each with whether it's a directory
+    /// symlinks to directories are not followed, to avoid walking in circles
+    /// stops once [cancelled](AtomicBool) is set or [send](FnMut) returns `false`
+    pub fn walk(
+        root: &Path,
+        cancelled: &AtomicBool,
+        send: &mut dyn FnMut(Vec<(PathBuf, bool)>) -> bool,
+    ) {
+        let mut dirs: VecDeque<PathBuf> = VecDeque::from([root.to_path_buf()]);
+        let mut batch: Vec<(PathBuf, bool)> = Vec::with_capacity(BATCH_SIZE);
+        let mut last_send = Instant::now();
+
+        while let Some(dir) = dirs.pop_front() {
+            // directories that can't be read are skipped
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+
+            for entry in entries.flatten() {
+                if cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                let path = entry.path();
+                let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+                let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+
+                batch.push((relative, is_dir));
+                if is_dir {
+                    dirs.push_back(path);
+                }
+
+                // small trees still show up right away
+                if batch.len() == BATCH_SIZE || last_send.elapsed() > Duration::from_millis(50) {
+                    if !send(std::mem::take(&mut batch)) {
+                        return;
+                    }
+                    last_send = Instant::now();
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            send(batch);
+        }
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let style = Theme::default();
+
+        let status = self.results.len().to_string()
+            + if self.results.len() == MAX_RESULTS {
+                "+"
+            } else {
+                ""
+            }
+            + "/"
+            + &self.candidates.len().to_string()
+            + if self.walking { " searching..." } else { "" };
+
+        let block = Block::bordered()
+            .title(" Find in ".to_string() + &Dir::get_shortened_path(self.root.clone()) + " ")
+            .title(Line::from(" [Enter] open  [Up/Down] move  [Esc] close ").centered())
+            .title(Line::from(" ".to_string() + &status + " ").right_aligned())
+            .title_position(Position::Top);
+
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Length(1), Constraint::Fill(1)])
+            .split(inner);
+
+        let input_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Length(2), Constraint::Fill(1)])
+            .split(layout[0]);
+
+        Paragraph::new("> ").render(input_layout[0], buf);
+        self.input.render(input_layout[1], buf);
+
+        // only the rows that fit are highlighted, positions are not kept for every result
+        // a terminal a few lines tall leaves no room for the list, it's still laid out as one row so
+        // the cursor stays inside it
+        let rows = (layout[1].height as usize).max(1);
+        let offset = (self.index + 1).saturating_sub(rows);
+
+        let lines: Vec<Line> = self
+            .results
+            .iter()
+            .skip(offset)
+            .take(rows)
+            .map(|(_, index)| {
+                let candidate = &self.candidates[*index];
+                let positions = self
+                    .matcher
+                    .score(candidate)
+                    .map(|(_, positions)| positions)
+                    .unwrap_or_default();
+
+                Line::from(
+                    candidate
+                        .chars()
+                        .enumerate()
+                        .map(|(position, c)| match positions.contains(&position) {
+                            true => Span::styled(c.to_string(), style.matched),
+                            false => Span::raw(c.to_string()),
+                        })
+                        .collect::<Vec<Span>>(),
+                )
+            })
+            .collect();
+
+        let mut state = ListState::default().with_selected(Some(self.index - offset));
+
+        StatefulWidget::render(
+            List::new(lines)
+                .style(style.normal)
+                .highlight_style(style.selected),
+            layout[1],
+            buf,
+            &mut state,
+        );
+    }
+}
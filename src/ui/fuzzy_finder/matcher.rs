@@ -0,0 +1,213 @@
+//! Fuzzy matching of a query against paths, scored in the same spirit as fzf
+//!
+//! The query chars must appear in the candidate in order, but not necessarily next to each other.
+//! Matches get more points when the chars are consecutive, at the start of words or inside the
+//! file name, and lose points for the gaps between them.
+//!
+//! # Example
+//! ```rust
+//! let matcher = Matcher::new("fpick");
+//!
+//! if let Some((score, positions)) = matcher.score("src/ui/file_picker/file_picker.rs") {
+//!     println!("{score}: {positions:?}");
+//! }
+//! ```
+
+const SCORE_MATCH: i64 = 16;
+const SCORE_GAP_START: i64 = -3;
+const SCORE_GAP_EXTENSION: i64 = -1;
+const BONUS_CONSECUTIVE: i64 = 4;
+const BONUS_AFTER_SLASH: i64 = 10;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_CAMEL_CASE: i64 = 7;
+const BONUS_FIRST_CHAR: i64 = 2;
+const BONUS_FILE_NAME: i64 = 2;
+
+/// [query](Vec<char>): the chars to match, lowercased unless [case_sensitive](bool)
+/// [case_sensitive](bool): smart case, only if the query has some uppercase char
+pub struct Matcher {
+    query: Vec<char>,
+    case_sensitive: bool,
+}
+
+impl Matcher {
+    pub fn new(query: &str) -> Matcher {
+        let case_sensitive = query.chars().any(|c| c.is_uppercase());
+        Matcher {
+            query: query
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| Matcher::normalize(c, case_sensitive))
+                .collect(),
+            case_sensitive,
+        }
+    }
+
+    /// returns the score of [candidate](str) and the indexes of the chars matched, or `None` if it
+    /// doesn't match. higher scores are better matches
+    pub fn score(&self, candidate: &str) -> Option<(i64, Vec<usize>)> {
+        if self.query.is_empty() {
+            return Some((0, Vec::new()));
+        }
+
+        let chars: Vec<char> = candidate.chars().collect();
+        let normalized: Vec<char> = chars
+            .iter()
+            .map(|c| Matcher::normalize(*c, self.case_sensitive))
+            .collect();
+
+        // first pass: where the earliest full match ends
+        let mut query_index = 0;
+        let mut end = None;
+        for (index, c) in normalized.iter().enumerate() {
+            if *c == self.query[query_index] {
+                query_index += 1;
+                if query_index == self.query.len() {
+                    end = Some(index);
+                    break;
+                }
+            }
+        }
+        let end = end?;
+
+        // second pass: walks back from the end to find the shortest match ending there
+        let mut query_index = self.query.len() - 1;
+        let mut start = end;
+        for index in (0..=end).rev() {
+            if normalized[index] == self.query[query_index] {
+                if query_index == 0 {
+                    start = index;
+                    break;
+                }
+                query_index -= 1;
+            }
+        }
+
+        // the match is taken again from the start, now inside the shortest window
+        let file_name_start = chars
+            .iter()
+            .rposition(|c| *c == '/')
+            .map(|index| index + 1)
+            .unwrap_or(0);
+
+        let mut positions = Vec::with_capacity(self.query.len());
+        let mut score = 0;
+        let mut query_index = 0;
+        let mut prev_match: Option<usize> = None;
+
+        for (index, c) in normalized.iter().enumerate().take(end + 1).skip(start) {
+            if query_index == self.query.len() {
+                break;
+            }
+            if *c != self.query[query_index] {
+                continue;
+            }
+
+            score += SCORE_MATCH + Matcher::get_bonus(&chars, index);
+            if index == 0 {
+                score += BONUS_FIRST_CHAR;
+            }
+            if index >= file_name_start {
+                score += BONUS_FILE_NAME;
+            }
+
+            match prev_match {
+                Some(prev) if prev + 1 == index => score += BONUS_CONSECUTIVE,
+                Some(prev) => {
+                    score += SCORE_GAP_START + SCORE_GAP_EXTENSION * (index - prev - 2) as i64
+                }
+                None => (),
+            }
+
+            positions.push(index);
+            prev_match = Some(index);
+            query_index += 1;
+        }
+
+        Some((score, positions))
+    }
+
+    /// extra points for chars at the start of a word, where people usually start typing from
+    fn get_bonus(chars: &[char], index: usize) -> i64 {
+        let Some(prev) = index.checked_sub(1).map(|prev| chars[prev]) else {
+            return BONUS_BOUNDARY;
+        };
+        let curr = chars[index];
+
+        if prev == '/' {
+            BONUS_AFTER_SLASH
+        } else if !prev.is_alphanumeric() && curr.is_alphanumeric() {
+            BONUS_BOUNDARY
+        } else if prev.is_lowercase() && curr.is_uppercase() {
+            BONUS_CAMEL_CASE
+        } else {
+            0
+        }
+    }
+
+    fn normalize(c: char, case_sensitive: bool) -> char {
+        match case_sensitive {
+            true => c,
+            false => c.to_lowercase().next().unwrap_or(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(query: &str, candidate: &str) -> i64 {
+        Matcher::new(query).score(candidate).unwrap().0
+    }
+
+    #[test]
+    fn query_chars_must_appear_in_order() {
+        let matcher = Matcher::new("fpk");
+
+        assert_eq!(
+            matcher
+                .score("file_picker.rs")
+                .map(|(_, positions)| positions),
+            Some(vec![0, 5, 8])
+        );
+        assert!(matcher.score("kpf").is_none());
+        assert!(matcher.score("fp").is_none());
+    }
+
+    #[test]
+    fn empty_queries_match_everything() {
+        assert_eq!(Matcher::new("").score("anything"), Some((0, Vec::new())));
+        assert_eq!(Matcher::new(" ").score(""), Some((0, Vec::new())));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(Matcher::new("readme").score("README.md").is_some());
+        assert!(Matcher::new("ReadMe").score("readme.md").is_none());
+        assert!(Matcher::new("ReadMe").score("ReadMe.md").is_some());
+    }
+
+    #[test]
+    fn shortest_match_is_scored() {
+        // the first 'a' is far from the 'b', the match starts at the one next to it
+        assert_eq!(
+            Matcher::new("ab")
+                .score("a____ab")
+                .map(|(_, positions)| positions),
+            Some(vec![5, 6])
+        );
+    }
+
+    #[test]
+    fn better_matches_score_higher() {
+        // consecutive chars beat scattered ones
+        assert!(score("pick", "picker") > score("pick", "pxixcxk"));
+        // word starts beat the middle of words
+        assert!(score("fp", "file_picker") > score("fp", "xfxp"));
+        // matches in the file name beat matches in the directories
+        assert!(score("main", "src/main.rs") > score("main", "main/src.rs"));
+        // camel case humps count as word starts
+        assert!(score("fp", "FilePicker") > score("fp", "Filepicker"));
+    }
+}
@@ -0,0 +1,388 @@
+//! Full screen search for a pattern inside the files under a directory
+//!
+//! Files are searched on a background task while the hits found so far are listed as
+//! `path:line: snippet`, with the file of the hit under the cursor previewed next to them. Binary
+//! files are skipped. The search can be cancelled at any time, keeping the hits already found.
+//!
+//! # Example
+//! ```rust
+//! let regex = Regex::new("TODO").unwrap();
+//!
+//! if let Some(path) = GrepView::show(PathBuf::from("/home/user/project"), regex, jobs.clone()) {
+//!     // jumps to the file of the hit chosen
+//!     file_picker.initialize(path.parent(), Some(Selection::Entry(path)));
+//! }
+//! ```
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Result},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    prelude::*,
+    widgets::{block::Position, Block, List, ListState, Paragraph},
+};
+use regex::Regex;
+use tokio::sync::mpsc::{error::TryRecvError, unbounded_channel, UnboundedReceiver};
+
+use crate::{
+    theme::Theme,
+    tui,
+    ui::{
+        file_picker::{dir::Dir, jobs::Jobs},
+        fuzzy_finder::fuzzy_finder::FuzzyFinder,
+        popup::popup::show_info,
+        preview_pane::preview_pane::PreviewPane,
+    },
+};
+
+/// the search stops after this many hits, a narrower pattern is needed past that
+const MAX_HITS: usize = 10_000;
+/// how much of the start of a file is checked for null bytes to tell if it's binary
+const BINARY_CHECK_SIZE: usize = 8192;
+/// longer lines are cut in the results list
+const MAX_SNIPPET_LENGTH: usize = 200;
+/// only the start of longer lines is searched, so a huge file with no line breaks isn't read into
+/// memory all at once
+const MAX_LINE_LENGTH: u64 = 64 * 1024;
+
+/// a line matching the pattern
+///
+/// [path](PathBuf): the file, relative to the directory searched
+/// [line](usize): the line number, starting at 1
+/// [snippet](String): the line itself, trimmed
+pub struct GrepHit {
+    pub path: PathBuf,
+    pub line: usize,
+    pub snippet: String,
+}
+
+/// [root](PathBuf): the directory being searched
+/// [pattern](String): what is being searched, as typed by the user
+/// [hits](Vec<GrepHit>): lines found so far
+/// [files_searched](usize): how many files were searched so far
+/// [receiver](UnboundedReceiver): files searched and hits found, sent by the search task
+/// [searching](bool): if the search task is still running
+/// [cancelled](AtomicBool): tells the search task to stop
+/// [index](usize): the index of the hit under the cursor
+/// [preview](PreviewPane): shows the file of the hit under the cursor
+/// [previewed](Option<usize>): the hit being previewed
+pub struct GrepView {
+    root: PathBuf,
+    pattern: String,
+    hits: Vec<GrepHit>,
+    files_searched: usize,
+    receiver: UnboundedReceiver<(usize, Vec<GrepHit>)>,
+    searching: bool,
+    cancelled: Arc<AtomicBool>,
+    index: usize,
+    preview: PreviewPane,
+    previewed: Option<usize>,
+}
+
+impl GrepView {
+    /// searches [regex](Regex) in every file under [root](PathBuf) and shows the hits until the
+    /// user picks one, whose file is returned, or closes the view
+    pub fn show(root: PathBuf, regex: Regex, jobs: Jobs) -> Option<PathBuf> {
+        let (sender, receiver) = unbounded_channel::<(usize, Vec<GrepHit>)>();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let search_root = root.clone();
+        let search_cancelled = cancelled.clone();
+        let pattern = regex.as_str().to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let mut total_hits = 0;
+            FuzzyFinder::walk(&search_root, &search_cancelled, &mut |batch| {
+                let mut hits = Vec::new();
+                let mut searched = 0;
+
+                for (relative, _) in batch.into_iter().filter(|(_, is_dir)| !is_dir) {
+                    // hits are counted as they're found, a single file can have lots of them
+                    let max_hits = MAX_HITS - total_hits;
+                    if search_cancelled.load(Ordering::Relaxed) || hits.len() >= max_hits {
+                        break;
+                    }
+                    let path = search_root.join(&relative);
+                    if GrepView::search_file(
+                        &path,
+                        &relative,
+                        &regex,
+                        &search_cancelled,
+                        &mut hits,
+                        max_hits,
+                    ) {
+                        searched += 1;
+                    }
+                }
+
+                total_hits += hits.len();
+                sender.send((searched, hits)).is_ok() && total_hits < MAX_HITS
+            });
+        });
+
+        let mut view = GrepView {
+            root,
+            pattern,
+            hits: Vec::new(),
+            files_searched: 0,
+            receiver,
+            searching: true,
+            cancelled,
+            index: 0,
+            preview: PreviewPane::new(jobs),
+            previewed: None,
+        };
+
+        // try catch
+        let res = view.run().unwrap_or_else(|error| {
+            show_info("Error displaying search", error.to_string());
+            None
+        });
+
+        view.cancelled.store(true, Ordering::Relaxed);
+        res
+    }
+
+    fn run(&mut self) -> Result<Option<PathBuf>> {
+        let mut term = tui::init()?;
+        term.clear()?;
+
+        let mut needs_draw = true;
+
+        loop {
+            if needs_draw {
+                self.update_preview();
+                term.draw(|frame| self.render(frame.size(), frame.buffer_mut()))?;
+            }
+
+            needs_draw = false;
+            if event::poll(Duration::from_millis(50))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                        match key.code {
+                            KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                            KeyCode::Enter => {
+                                return Ok(self
+                                    .hits
+                                    .get(self.index)
+                                    .map(|hit| self.root.join(&hit.path)))
+                            }
+                            // [Ctrl+c] stops searching, keeping the hits found until now
+                            KeyCode::Char('c') if ctrl => {
+                                self.cancelled.store(true, Ordering::Relaxed)
+                            }
+                            KeyCode::Char('j') | KeyCode::Down => {
+                                self.index = (self.index + 1).min(self.hits.len().max(1) - 1)
+                            }
+                            KeyCode::Char('k') | KeyCode::Up => {
+                                self.index = self.index.saturating_sub(1)
+                            }
+                            KeyCode::Char('g') => self.index = 0,
+                            KeyCode::Char('G') => self.index = self.hits.len().max(1) - 1,
+                            _ => (),
+                        }
+                        needs_draw = true;
+                    }
+                }
+            }
+
+            needs_draw |= self.receive_hits();
+            needs_draw |= self.preview.receive_preview();
+            needs_draw |= self.preview.handle_fs_changes();
+        }
+    }
+
+    /// takes what the search task sent, returns `true` if there was anything
+    fn receive_hits(&mut self) -> bool {
+        let mut received = false;
+        loop {
+            match self.receiver.try_recv() {
+                Ok((searched, hits)) => {
+                    self.files_searched += searched;
+                    self.hits.extend(hits);
+                    received = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    received |= self.searching;
+                    self.searching = false;
+                    break;
+                }
+            }
+        }
+        received
+    }
+
+    /// previews the file of the hit under the cursor, if it's not the one being previewed already
+    fn update_preview(&mut self) {
+        if self.hits.is_empty() || self.previewed == Some(self.index) {
+            return;
+        }
+        let hit = &self.hits[self.index];
+        self.preview
+            .initialize_at_line(self.root.join(&hit.path), hit.line);
+        self.previewed = Some(self.index);
+    }
+
+    /// adds the lines of [path](Path) matching [regex](Regex) to [hits](Vec<GrepHit>), until it
+    /// has [max_hits](usize) of them
+    /// returns `false` if the file wasn't searched, because it's binary or can't be read
+    fn search_file(
+        path: &Path,
+        relative: &Path,
+        regex: &Regex,
+        cancelled: &AtomicBool,
+        hits: &mut Vec<GrepHit>,
+        max_hits: usize,
+    ) -> bool {
+        // fifos and devices would block or never end, symlinks are searched where they point to
+        if !path
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.is_file())
+        {
+            return false;
+        }
+        let Ok(file) = File::open(path) else {
+            return false;
+        };
+
+        let mut reader = BufReader::with_capacity(BINARY_CHECK_SIZE, file);
+        match reader.fill_buf() {
+            Ok(start) if !start.contains(&0) => (),
+            _ => return false,
+        }
+
+        let mut line = Vec::new();
+        let mut number = 0;
+
+        while let Ok(read) = reader
+            .by_ref()
+            .take(MAX_LINE_LENGTH)
+            .read_until(b'\n', &mut line)
+        {
+            if read == 0 || hits.len() >= max_hits {
+                break;
+            }
+            // the rest of a line that was too long is skipped without keeping it
+            if read as u64 == MAX_LINE_LENGTH && !line.ends_with(b"\n") {
+                let _ = reader.skip_until(b'\n');
+            }
+
+            number += 1;
+            if number % 1000 == 0 && cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let text = String::from_utf8_lossy(&line);
+            if regex.is_match(&text) {
+                hits.push(GrepHit {
+                    path: relative.to_path_buf(),
+                    line: number,
+                    snippet: text.trim().chars().take(MAX_SNIPPET_LENGTH).collect(),
+                });
+            }
+            line.clear();
+        }
+
+        true
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let style = Theme::default();
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+
+        let status = self.hits.len().to_string()
+            + if self.hits.len() >= MAX_HITS { "+" } else { "" }
+            + " hits in "
+            + &self.files_searched.to_string()
+            + " files"
+            + match (self.searching, self.cancelled.load(Ordering::Relaxed)) {
+                (true, true) => " cancelling...",
+                (true, false) => " searching...",
+                _ => "",
+            };
+
+        let block = Block::bordered()
+            .title(
+                " Search '".to_string()
+                    + &self.pattern
+                    + "' in "
+                    + &Dir::get_shortened_path(self.root.clone())
+                    + " ",
+            )
+            .title(Line::from(" ".to_string() + &status + " ").right_aligned())
+            .title_position(Position::Top)
+            .title_bottom(Line::from(" [Enter] open  [Ctrl+c] stop  [q] close ").centered());
+
+        let inner = block.inner(layout[0]);
+        block.render(layout[0], buf);
+
+        if self.hits.is_empty() {
+            Paragraph::new(if self.searching {
+                "Searching..."
+            } else {
+                "No matches"
+            })
+            .add_modifier(Modifier::DIM)
+            .render(inner, buf);
+        } else {
+            // only the rows that fit are built, there can be thousands of hits. a terminal 2 lines
+            // tall leaves no room for them, it's still laid out as one row so the cursor stays inside
+            let rows = (inner.height as usize).max(1);
+            let offset = (self.index + 1).saturating_sub(rows);
+
+            let lines: Vec<Line> = self
+                .hits
+                .iter()
+                .skip(offset)
+                .take(rows)
+                .map(|hit| {
+                    Line::from(vec![
+                        Span::styled(
+                            hit.path.to_string_lossy().to_string()
+                                + ":"
+                                + &hit.line.to_string()
+                                + ": ",
+                            Style::default().add_modifier(Modifier::DIM),
+                        ),
+                        Span::raw(hit.snippet.clone()),
+                    ])
+                })
+                .collect();
+
+            let mut state = ListState::default().with_selected(Some(self.index - offset));
+
+            StatefulWidget::render(
+                List::new(lines)
+                    .style(style.normal)
+                    .highlight_style(style.selected),
+                inner,
+                buf,
+                &mut state,
+            );
+        }
+
+        // the preview has nothing to show until the first hit
+        match self.previewed {
+            Some(_) => self.preview.render(layout[1], buf),
+            None => Block::bordered()
+                .add_modifier(Modifier::DIM)
+                .render(layout[1], buf),
+        }
+    }
+}
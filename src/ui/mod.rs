@@ -1,5 +1,12 @@
 pub mod app;
+pub mod bookmarks_view;
+pub mod dir_jump;
 pub mod file_picker;
+pub mod fuzzy_finder;
+pub mod grep_view;
+pub mod pane_layout;
 pub mod popup;
 pub mod preview_pane;
+pub mod text_input;
+pub mod trash_view;
 pub mod utils;
@@ -0,0 +1,85 @@
+//! Layouts the panels can be arranged in, cycled by the user
+//!
+//! # Example
+//! ```rust
+//! let areas = PaneLayout::Miller.split(area);
+//! if let Some(parent_area) = areas.parent {
+//!     parent_column.render(parent_area, buf);
+//! }
+//! file_picker.render(areas.picker, buf);
+//! ```
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+/// how the panels are arranged
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PaneLayout {
+    /// the file picker on the left and the preview on the right
+    Split,
+    /// like ranger: the parent directory, the file picker and the preview
+    Miller,
+    /// only the file picker, using the whole width
+    PickerOnly,
+}
+
+/// where each panel goes, panels that aren't part of the layout are `None`
+///
+/// [parent](Option<Rect>): the listing of the parent directory
+/// [picker](Rect): the file picker
+/// [preview](Option<Rect>): the preview pane
+pub struct PaneAreas {
+    pub parent: Option<Rect>,
+    pub picker: Rect,
+    pub preview: Option<Rect>,
+}
+
+impl PaneLayout {
+    /// the layout that comes after this one when cycling through them
+    pub fn next(self) -> PaneLayout {
+        match self {
+            PaneLayout::Split => PaneLayout::Miller,
+            PaneLayout::Miller => PaneLayout::PickerOnly,
+            PaneLayout::PickerOnly => PaneLayout::Split,
+        }
+    }
+
+    pub fn has_preview(self) -> bool {
+        self != PaneLayout::PickerOnly
+    }
+
+    /// divides [area](Rect) between the panels of the layout
+    pub fn split(self, area: Rect) -> PaneAreas {
+        let constraints = match self {
+            PaneLayout::Split => vec![Constraint::Percentage(40), Constraint::Percentage(60)],
+            PaneLayout::Miller => vec![
+                Constraint::Percentage(15),
+                Constraint::Percentage(30),
+                Constraint::Percentage(55),
+            ],
+            PaneLayout::PickerOnly => vec![Constraint::Percentage(100)],
+        };
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints)
+            .split(area);
+
+        match self {
+            PaneLayout::Split => PaneAreas {
+                parent: None,
+                picker: layout[0],
+                preview: Some(layout[1]),
+            },
+            PaneLayout::Miller => PaneAreas {
+                parent: Some(layout[0]),
+                picker: layout[1],
+                preview: Some(layout[2]),
+            },
+            PaneLayout::PickerOnly => PaneAreas {
+                parent: None,
+                picker: layout[0],
+                preview: None,
+            },
+        }
+    }
+}
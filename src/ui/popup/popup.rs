@@ -13,33 +13,65 @@
 
 use std::io::{Error, Result};
 
-use crossterm::event::{self, KeyCode};
+use crossterm::event::{self, KeyCode, KeyEventKind};
 use ratatui::{
-    layout::Alignment,
+    layout::{Alignment, Rect},
     style::{Color, Modifier, Style},
     widgets::{
         block::{self, Position},
-        Block, Borders, Paragraph, Wrap,
+        Block, Borders, Clear, Paragraph, Wrap,
     },
 };
 
-use crate::{tui, ui::utils::Utils};
+use crate::{
+    tui,
+    ui::{
+        file_picker::file_ops::ConflictResolution, text_input::text_input::TextInput, utils::Utils,
+    },
+};
 
 pub static KEYBINDINGS_INFO: &str = "[?]         - Show this window
 [j/k]       - Navigate up/down in list
 [l]         - Open directory/file
 [h]         - Go to parent directory
+[H/L]       - Go back/forward in the directories visited
 [Space]     - Adds/Removes directory/files to/from buffer
-[d]         - Delete directory/file
+[d]         - Move directory/file to the trash
+[D]         - Permanently delete directory/file
+[z]         - Jump to a visited directory, ranked by frecency
+[:]         - Go to a directory, [Tab] completes its name
+[f]         - Fuzzy find an entry in any directory under the current one
+[s/S]       - Search text/regex in the files under the current directory
+[/]         - Filter entries, [Enter] keeps the filter, [Esc] clears it
+[m<key>]    - Bookmark the current directory under <key>
+['<key>]    - Go to the directory bookmarked under <key>
+[B]         - List the bookmarks, to open, delete or reorder them
+[T]         - Show the trash, to restore or delete trashed entries
+[r]         - Rename directory/file
+[n/N]       - Create a new file/directory, nested paths are allowed
+[u]         - Undo the last file operation
+[Ctrl+r]    - Redo the last undone file operation
 [bc]        - Clears buffer, ie: unselects all
-[bd]        - Deletes all files in buffer
-[Ctrl+ h/l] - Switch selected panel";
+[bp]        - Pastes a copy of all files in buffer
+[bm]        - Moves all files in buffer here
+[bd]        - Moves all files in buffer to the trash
+[bD]        - Permanently deletes all files in buffer
+[br]        - Renames all files in buffer with $EDITOR
+[1-9]       - Switch to a tab, the number after the last tab opens a new one
+[Ctrl+w]    - Close the current tab
+[v]         - Switch layout: picker/preview, parent/picker/preview or picker only
+[Ctrl+ h/l] - Switch selected panel
+[j/k]       - In the preview panel, scroll the file a line down/up
+[Ctrl+ d/u] - In the preview panel, scroll the file half a page down/up
+[g/G]       - In the preview panel, go to the start/end of the file
+[Ctrl+c]    - Cancel running and queued jobs";
 
 pub fn show_error(title: &str, error: Error) {
     show_info(title, error.to_string().clone());
 }
 
 /// shows a floating window in the center of screen
+/// info taller than the screen can be scrolled with j/k
 ///
 /// +---title---+
 /// ∣           ∣
@@ -51,32 +83,59 @@ pub fn show_info(title: &str, info: String) {
     if let Err(error) = (|| -> Result<()> {
         //try block
         let mut term = tui::init()?;
-        term.draw(|frame| {
-            let block =
-                Block::default() // block to wrap around message
-                    .title(block::Title::from(title).alignment(Alignment::Center))
-                    .title(
-                        // bottom message for user
-                        block::Title::from("Press any key to close")
-                            .alignment(Alignment::Center)
-                            .position(Position::Bottom),
-                    )
+        let mut scroll: u16 = 0;
+
+        loop {
+            // only known once drawn, as it depends on the size of the screen
+            let mut max_scroll: u16 = 0;
+
+            term.draw(|frame| {
+                // calculates the width of the longest line in message
+                let info_size = info.clone().chars().filter(|c| *c == '\n').count();
+                let inner = Block::default()
                     .borders(Borders::ALL)
-                    .title_style(Style::default().add_modifier(Modifier::BOLD));
+                    .inner(Utils::centered_rect(
+                        65,
+                        u16::try_from(info_size)
+                            .unwrap_or(u16::MAX)
+                            .saturating_add(5),
+                        frame.size(),
+                    ));
 
-            // calculates the width of the longest line in message
-            let info_size = info.clone().chars().filter(|c| *c == '\n').count();
-            let paragraph = Paragraph::new(info.clone()).wrap(Wrap { trim: false }); //message body
+                max_scroll = get_max_scroll(&info, inner);
+                scroll = scroll.min(max_scroll);
 
-            let inner = block.inner(Utils::centered_rect(65, 5 + info_size as u16, frame.size()));
+                let block =
+                    Block::default() // block to wrap around message
+                        .title(block::Title::from(title).alignment(Alignment::Center))
+                        .title(
+                            // bottom message for user
+                            block::Title::from(match max_scroll {
+                                0 => "Press any key to close",
+                                _ => "[j/k] scroll, any other key to close",
+                            })
+                            .alignment(Alignment::Center)
+                            .position(Position::Bottom),
+                        )
+                        .borders(Borders::ALL)
+                        .title_style(Style::default().add_modifier(Modifier::BOLD));
 
-            frame.render_widget(paragraph.clone().block(block), inner);
-        })?;
+                let paragraph = Paragraph::new(info.clone())
+                    .wrap(Wrap { trim: false })
+                    .scroll((scroll, 0)); //message body
+
+                frame.render_widget(paragraph.block(block), inner);
+            })?;
 
-        loop {
             if event::poll(std::time::Duration::from_millis(16))? {
-                if let event::Event::Key(..) = event::read()? {
-                    break; // closes popup on any keypress
+                if let event::Event::Key(key) = event::read()? {
+                    match key.code {
+                        KeyCode::Char('j') | KeyCode::Down if max_scroll > 0 => scroll += 1,
+                        KeyCode::Char('k') | KeyCode::Up if max_scroll > 0 => {
+                            scroll = scroll.saturating_sub(1)
+                        }
+                        _ => break, // closes popup on any other keypress
+                    }
                 }
             }
         }
@@ -90,6 +149,7 @@ pub fn show_info(title: &str, info: String) {
 
 /// shows a floating popup with Yes and No options and returns `true` if the user selects Yes
 /// defaults to `false` if an error occur while rendering the popup
+/// info taller than the screen can be scrolled with j/k
 ///
 /// +---title---+
 /// ∣           ∣
@@ -101,56 +161,73 @@ pub fn show_confirmation(title: &str, info: String) -> bool {
     // try catch
     match (|| -> Result<bool> {
         let mut term = tui::init()?;
-        term.draw(|frame| {
-            //outside block with the Yes/No options
-            let block = Block::default()
-                .title(block::Title::from(title).alignment(Alignment::Center))
-                .borders(Borders::ALL)
-                .title_style(
-                    Style::default()
-                        .add_modifier(Modifier::BOLD)
-                        .fg(Color::White),
-                );
+        let mut scroll: u16 = 0;
+        loop {
+            let mut max_scroll: u16 = 0;
+
+            term.draw(|frame| {
+                //outside block with the Yes/No options
+                let block = Block::default()
+                    .title(block::Title::from(title).alignment(Alignment::Center))
+                    .borders(Borders::ALL)
+                    .title_style(
+                        Style::default()
+                            .add_modifier(Modifier::BOLD)
+                            .fg(Color::White),
+                    );
+
+                let info_size = text.clone().chars().filter(|c| *c == '\n').count();
 
-            // inside text
-            let paragraph = Paragraph::new(text.clone()).style(Style::default().fg(Color::Yellow));
-            let info_size = text.clone().chars().filter(|c| *c == '\n').count();
-
-            let area = Utils::centered_rect(25, 5 + info_size as u16, frame.size());
-            let inner = block.inner(area);
-
-            frame.render_widget(paragraph.clone().block(block), inner);
-
-            let yes = Block::default()
-                .title(
-                    block::Title::from("  [Y]es")
-                        .alignment(Alignment::Left)
-                        .position(Position::Bottom),
-                )
-                .title_style(
-                    Style::default()
-                        .add_modifier(Modifier::BOLD)
-                        .fg(Color::Green),
+                let area = Utils::centered_rect(
+                    25,
+                    u16::try_from(info_size)
+                        .unwrap_or(u16::MAX)
+                        .saturating_add(5),
+                    frame.size(),
                 );
-            frame.render_widget(yes.clone(), area);
-
-            let no = Block::default()
-                .title(
-                    block::Title::from("[N]o  ")
-                        .alignment(Alignment::Right)
-                        .position(Position::Bottom),
-                )
-                .title_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Red));
-            frame.render_widget(no.clone(), area);
-        })?;
+                let inner = block.inner(area);
+
+                max_scroll = get_max_scroll(&text, inner);
+                scroll = scroll.min(max_scroll);
+
+                // inside text
+                let paragraph = Paragraph::new(text.clone())
+                    .style(Style::default().fg(Color::Yellow))
+                    .scroll((scroll, 0));
+
+                frame.render_widget(paragraph.block(block), inner);
+
+                let yes = Block::default()
+                    .title(
+                        block::Title::from("  [Y]es")
+                            .alignment(Alignment::Left)
+                            .position(Position::Bottom),
+                    )
+                    .title_style(
+                        Style::default()
+                            .add_modifier(Modifier::BOLD)
+                            .fg(Color::Green),
+                    );
+                frame.render_widget(yes.clone(), area);
+
+                let no = Block::default()
+                    .title(
+                        block::Title::from("[N]o  ")
+                            .alignment(Alignment::Right)
+                            .position(Position::Bottom),
+                    )
+                    .title_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Red));
+                frame.render_widget(no.clone(), area);
+            })?;
 
-        loop {
             // stays in loop until user press one of the keys
             if event::poll(std::time::Duration::from_millis(16))? {
                 if let event::Event::Key(key) = event::read()? {
                     match key.code {
                         KeyCode::Char('y') => return Ok(true),
                         KeyCode::Char('n') => return Ok(false),
+                        KeyCode::Char('j') | KeyCode::Down => scroll = (scroll + 1).min(max_scroll),
+                        KeyCode::Char('k') | KeyCode::Up => scroll = scroll.saturating_sub(1),
                         _ => continue,
                     };
                 }
@@ -166,3 +243,216 @@ pub fn show_confirmation(title: &str, info: String) -> bool {
         }
     }
 }
+
+/// shows a floating popup asking what to do with an entry that already exists in the destination
+/// returns the chosen [ConflictResolution] and whether it should be applied to all remaining
+/// conflicts, or `None` if the user cancels (or an error occurs while rendering the popup)
+///
+/// +---title--------------------+
+/// ∣                            ∣
+/// ∣ info                       ∣
+/// ∣                            ∣
+/// ∣ [a]pply to all remaining   ∣
+/// +-[o]verwrite-[s]kip-[r]ename+
+pub fn show_conflict(title: &str, info: String) -> Option<(ConflictResolution, bool)> {
+    let mut apply_to_all = false;
+    // try catch
+    match (|| -> Result<Option<(ConflictResolution, bool)>> {
+        let mut term = tui::init()?;
+        loop {
+            let text = "\n".to_string()
+                + &info.clone()
+                + "\n\n[a]pply to all remaining: "
+                + if apply_to_all { "[x]" } else { "[ ]" };
+
+            term.draw(|frame| {
+                let block = Block::default()
+                    .title(block::Title::from(title).alignment(Alignment::Center))
+                    .title(
+                        block::Title::from(" [o]verwrite  [s]kip  [r]ename  [Esc] cancel ")
+                            .alignment(Alignment::Center)
+                            .position(Position::Bottom),
+                    )
+                    .borders(Borders::ALL)
+                    .title_style(
+                        Style::default()
+                            .add_modifier(Modifier::BOLD)
+                            .fg(Color::White),
+                    );
+
+                let paragraph = Paragraph::new(text.clone())
+                    .style(Style::default().fg(Color::Yellow))
+                    .wrap(Wrap { trim: false });
+                let info_size = text.clone().chars().filter(|c| *c == '\n').count();
+
+                let area = Utils::centered_rect(
+                    65,
+                    u16::try_from(info_size)
+                        .unwrap_or(u16::MAX)
+                        .saturating_add(5),
+                    frame.size(),
+                );
+
+                frame.render_widget(paragraph.clone().block(block), area);
+            })?;
+
+            // stays in loop until user press one of the keys
+            if event::poll(std::time::Duration::from_millis(16))? {
+                if let event::Event::Key(key) = event::read()? {
+                    let resolution = match key.code {
+                        KeyCode::Char('o') => ConflictResolution::Overwrite,
+                        KeyCode::Char('s') => ConflictResolution::Skip,
+                        KeyCode::Char('r') => ConflictResolution::Rename,
+                        KeyCode::Char('a') => {
+                            apply_to_all = !apply_to_all;
+                            continue;
+                        }
+                        KeyCode::Esc => return Ok(None),
+                        _ => continue,
+                    };
+                    return Ok(Some((resolution, apply_to_all)));
+                }
+            }
+        }
+    })() {
+        Ok(res) => res,
+        Err(error) => {
+            println!(
+                "Error displaying information: {error:?}\n Original information: {title}:{info}"
+            );
+            None
+        }
+    }
+}
+
+/// shows a floating popup where the user can edit [input](TextInput)
+/// returns the text once the user presses Enter and [validate](Fn) returns `None` for it, otherwise
+/// the error returned is shown under the input and the popup stays open
+/// returns `None` if the user cancels (or an error occurs while rendering the popup)
+///
+/// +---title--------------------+
+/// ∣ input                      ∣
+/// ∣ error                      ∣
+/// +--[Enter] confirm [Esc] cancel+
+pub fn show_input(
+    title: &str,
+    input: TextInput,
+    validate: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    show_input_with_completion(title, input, validate, |value| {
+        (value.to_string(), Vec::new())
+    })
+}
+
+/// same as [show_input], but pressing Tab replaces the text with the one returned by
+/// [complete](Fn), which also returns the possible completions to be listed under the input when
+/// there is more than one
+pub fn show_input_with_completion(
+    title: &str,
+    mut input: TextInput,
+    validate: impl Fn(&str) -> Option<String>,
+    complete: impl Fn(&str) -> (String, Vec<String>),
+) -> Option<String> {
+    // the line under the input, either an error or the possible completions
+    let mut message: Option<(String, Style)> = None;
+    // try catch
+    match (|| -> Result<Option<String>> {
+        let mut term = tui::init()?;
+        loop {
+            term.draw(|frame| {
+                let block = Block::default()
+                    .title(block::Title::from(title).alignment(Alignment::Center))
+                    .title(
+                        block::Title::from(" [Enter] confirm  [Esc] cancel ")
+                            .alignment(Alignment::Center)
+                            .position(Position::Bottom),
+                    )
+                    .borders(Borders::ALL)
+                    .title_style(
+                        Style::default()
+                            .add_modifier(Modifier::BOLD)
+                            .fg(Color::White),
+                    );
+
+                let area = Utils::centered_rect(65, 4, frame.size());
+                let inner = block.inner(area);
+
+                // the popup is drawn by a new terminal that doesn't know what is under it, so the
+                // cells need a style for the blank ones to be actually drawn over the old content
+                frame.render_widget(Clear, area);
+                frame.render_widget(
+                    Block::default().style(Style::default().fg(Color::White)),
+                    area,
+                );
+                frame.render_widget(block, area);
+                frame.render_widget(&input, Rect { height: 1, ..inner });
+
+                if let Some((message, style)) = &message {
+                    frame.render_widget(
+                        Paragraph::new(message.clone()).style(*style),
+                        Rect {
+                            y: inner.y + 1,
+                            height: 1,
+                            ..inner
+                        },
+                    );
+                }
+            })?;
+
+            // stays in loop until the user confirms a valid text or cancels
+            if let event::Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Enter => {
+                        let value = input.get_value();
+                        match validate(&value) {
+                            Some(error) => {
+                                message = Some((error, Style::default().fg(Color::Red)));
+                            }
+                            None => return Ok(Some(value)),
+                        }
+                    }
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Tab => {
+                        let (completed, candidates) = complete(&input.get_value());
+                        input.set_value(&completed);
+                        message = match candidates.len() {
+                            0 | 1 => None,
+                            _ => Some((
+                                candidates.join("  "),
+                                Style::default().add_modifier(Modifier::DIM),
+                            )),
+                        };
+                    }
+                    _ => {
+                        // the message is about the old text, so it goes away once it's edited
+                        if input.handle_keys(key) {
+                            message = None;
+                        }
+                    }
+                }
+            }
+        }
+    })() {
+        Ok(res) => res,
+        Err(error) => {
+            println!("Error displaying input: {error:?}\n Original title: {title}");
+            None
+        }
+    }
+}
+
+/// how many lines [text](str) has to be scrolled down to see its end when rendered wrapped inside
+/// a bordered block in [area](Rect), `0` if it fits
+fn get_max_scroll(text: &str, area: Rect) -> u16 {
+    let width = area.width.saturating_sub(2).max(1) as usize;
+    let lines: usize = text
+        .lines()
+        .map(|line| line.chars().count().div_ceil(width).max(1))
+        .sum();
+
+    let lines = u16::try_from(lines).unwrap_or(u16::MAX);
+    lines.saturating_sub(area.height.saturating_sub(2))
+}
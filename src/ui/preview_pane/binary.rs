@@ -0,0 +1,180 @@
+//! Tells binary files apart from text and shows them as a hex dump
+//!
+//! A file is taken as binary if the start of it has a null byte, or too much of it isn't valid
+//! UTF-8. Binary files are shown like `xxd`, with the type of the file found by its first bytes.
+//!
+//! # Example
+//! ```rust
+//! if Binary::is_binary(&start) {
+//!     let file_type = Binary::get_file_type(&start);
+//!     let lines = Binary::get_hex_lines(&bytes, 0);
+//! }
+//! ```
+
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+/// how much of the start of a file is checked to tell if it's binary
+pub const BINARY_CHECK_SIZE: usize = 8192;
+
+/// how many bytes are shown in each line of the hex dump
+pub const BYTES_PER_LINE: usize = 16;
+
+/// text with more than this share of bytes that aren't valid UTF-8 is taken as binary, a few
+/// are allowed for files in other encodings or with a broken character
+const MAX_INVALID_RATIO: f64 = 0.1;
+
+/// the first bytes of common file formats, and their name. [BINARY_CHECK_SIZE] bytes are checked
+const MAGIC_NUMBERS: [(usize, &[u8], &str); 22] = [
+    (0, b"\x7fELF", "ELF executable"),
+    (0, b"MZ", "Windows executable"),
+    (0, b"\xca\xfe\xba\xbe", "Java class"),
+    (0, b"\xcf\xfa\xed\xfe", "Mach-O executable"),
+    (0, b"\0asm", "WebAssembly module"),
+    (0, b"%PDF", "PDF document"),
+    (0, b"PK\x03\x04", "Zip archive"),
+    (0, b"\x1f\x8b", "gzip archive"),
+    (0, b"BZh", "bzip2 archive"),
+    (0, b"\xfd7zXZ\0", "xz archive"),
+    (0, b"\x28\xb5\x2f\xfd", "zstd archive"),
+    (0, b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (257, b"ustar", "tar archive"),
+    (0, b"SQLite format 3\0", "SQLite database"),
+    (0, b"\x89PNG", "PNG image"),
+    (0, b"\xff\xd8\xff", "JPEG image"),
+    (0, b"GIF8", "GIF image"),
+    (0, b"RIFF", "RIFF media"),
+    (0, b"OggS", "Ogg media"),
+    (0, b"fLaC", "FLAC audio"),
+    (0, b"ID3", "MP3 audio"),
+    (4, b"ftyp", "MP4 media"),
+];
+
+pub struct Binary {}
+
+impl Binary {
+    /// `true` if [start](u8), the start of a file, doesn't look like text
+    pub fn is_binary(start: &[u8]) -> bool {
+        if start.contains(&0) {
+            return true;
+        }
+        if start.is_empty() {
+            return false;
+        }
+
+        let invalid: usize = start.utf8_chunks().map(|chunk| chunk.invalid().len()).sum();
+        invalid as f64 / start.len() as f64 > MAX_INVALID_RATIO
+    }
+
+    /// the type of the file starting with [start](u8), by the magic number of its format
+    pub fn get_file_type(start: &[u8]) -> &'static str {
+        MAGIC_NUMBERS
+            .iter()
+            .find(|(offset, magic, _)| start[(*offset).min(start.len())..].starts_with(magic))
+            .map(|(_, _, name)| *name)
+            .unwrap_or("binary data")
+    }
+
+    /// [bytes](u8), found at [offset](u64) in the file, as lines like `xxd`'s:
+    /// `00000010  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|`
+    pub fn get_hex_lines(bytes: &[u8], offset: u64) -> Vec<Line<'static>> {
+        let dim = Style::default().add_modifier(Modifier::DIM);
+
+        bytes
+            .chunks(BYTES_PER_LINE)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut hex = String::new();
+                for position in 0..BYTES_PER_LINE {
+                    // the two halves are split to make it easier to count
+                    if position == BYTES_PER_LINE / 2 {
+                        hex += " ";
+                    }
+                    hex += &match chunk.get(position) {
+                        Some(byte) => format!("{:02x} ", byte),
+                        None => "   ".to_string(),
+                    };
+                }
+
+                let ascii: String = chunk
+                    .iter()
+                    .map(|byte| match byte.is_ascii_graphic() || *byte == b' ' {
+                        true => *byte as char,
+                        false => '.',
+                    })
+                    .collect();
+
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:08x}  ", offset + (index * BYTES_PER_LINE) as u64),
+                        dim,
+                    ),
+                    Span::raw(hex),
+                    Span::styled(" |".to_string() + &ascii + "|", dim),
+                ])
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_not_binary() {
+        assert!(!Binary::is_binary(b""));
+        assert!(!Binary::is_binary(b"fn main() {}\n"));
+        assert!(!Binary::is_binary("añob ✓\n".as_bytes()));
+    }
+
+    #[test]
+    fn null_bytes_are_binary() {
+        assert!(Binary::is_binary(b"text\0text"));
+        assert!(Binary::is_binary(b"\0"));
+    }
+
+    #[test]
+    fn a_few_invalid_bytes_are_still_text() {
+        // a latin-1 'ñ' in otherwise plain text
+        let mut text = b"a".repeat(99);
+        text.push(0xf1);
+        assert!(!Binary::is_binary(&text));
+
+        let noise: Vec<u8> = (0..100).map(|byte| 0x80 + byte % 0x40).collect();
+        assert!(Binary::is_binary(&noise));
+    }
+
+    #[test]
+    fn file_types_are_found_by_their_magic_number() {
+        assert_eq!(Binary::get_file_type(b"\x7fELF\x02\x01"), "ELF executable");
+        assert_eq!(Binary::get_file_type(b"\x89PNG\r\n"), "PNG image");
+        assert_eq!(Binary::get_file_type(b"\0\0\0\x18ftypmp42"), "MP4 media");
+
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(Binary::get_file_type(&tar), "tar archive");
+
+        assert_eq!(Binary::get_file_type(b"\0\x01"), "binary data");
+        assert_eq!(Binary::get_file_type(b""), "binary data");
+    }
+
+    #[test]
+    fn hex_lines_look_like_xxd() {
+        let lines = Binary::get_hex_lines(b"\x7fELF abc", 16);
+        let text: String = lines[0]
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+
+        assert_eq!(lines.len(), 1);
+        // the missing bytes are left blank, so the ascii column always lines up
+        assert_eq!(
+            text,
+            "00000010  7f 45 4c 46 20 61 62 63  ".to_string() + &"   ".repeat(8) + " |.ELF abc|"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Tells which of the entries being previewed changed on disk
+//!
+//! Only the directories given to [FsWatcher::watch] are watched, not their subdirectories, which
+//! is enough to notice changes to the previewed entry and to the listing it's in.
+//!
+//! # Example
+//! ```rust
+//! let mut watcher = FsWatcher::new();
+//! watcher.watch(vec![dir]);
+//!
+//! // later, without blocking
+//! for path in watcher.take_changes() {
+//!     cache.invalidate(&path);
+//! }
+//! ```
+
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+};
+
+use notify::{
+    event::{AccessKind, AccessMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+
+/// [watcher](Option<RecommendedWatcher>): `None` if the platform couldn't start watching, changes
+/// are then never reported
+/// [receiver](Receiver<notify::Result<Event>>): where the watcher sends the changes
+/// [watched](Vec<PathBuf>): the paths being watched
+pub struct FsWatcher {
+    watcher: Option<RecommendedWatcher>,
+    receiver: Receiver<notify::Result<Event>>,
+    watched: Vec<PathBuf>,
+}
+
+impl Default for FsWatcher {
+    fn default() -> Self {
+        FsWatcher::new()
+    }
+}
+
+impl FsWatcher {
+    pub fn new() -> FsWatcher {
+        let (sender, receiver) = mpsc::channel();
+
+        FsWatcher {
+            watcher: notify::recommended_watcher(sender).ok(),
+            receiver,
+            watched: Vec::new(),
+        }
+    }
+
+    /// watches [paths](Vec<PathBuf>) instead of the ones watched before
+    pub fn watch(&mut self, paths: Vec<PathBuf>) {
+        if paths == self.watched {
+            return;
+        }
+        let Some(watcher) = self.watcher.as_mut() else {
+            return;
+        };
+
+        for path in self.watched.iter() {
+            // it may have been deleted, which already stops the watch
+            let _ = watcher.unwatch(path);
+        }
+        self.watched = paths
+            .into_iter()
+            .filter(|path| watcher.watch(path, RecursiveMode::NonRecursive).is_ok())
+            .collect();
+    }
+
+    /// the paths that changed since the last call, without blocking
+    pub fn take_changes(&mut self) -> Vec<PathBuf> {
+        let mut changes: Vec<PathBuf> = Vec::new();
+
+        for event in self.receiver.try_iter().flatten() {
+            match event.kind {
+                // reading a file doesn't change it, but closing it after writing does
+                EventKind::Access(AccessKind::Close(AccessMode::Write)) => {}
+                EventKind::Access(_) => continue,
+                _ => {}
+            }
+            for path in event.paths {
+                if !changes.contains(&path) {
+                    changes.push(path);
+                }
+            }
+        }
+
+        changes
+    }
+}
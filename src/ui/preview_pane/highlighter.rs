@@ -0,0 +1,195 @@
+//! Highlights the syntax of the text preview, like bat, with line numbers on the left
+//!
+//! The language is chosen by, in order: a vim or emacs modeline, the file's extension or name and
+//! its first line, which catches scripts with a shebang. Grammars are the ones bundled with
+//! syntect, the colors are the ones in [Theme], so code looks like the rest of the app.
+//!
+//! # Example
+//! ```rust
+//! let cancelled = AtomicBool::new(false);
+//! if let Some(text) = Highlighter::highlight(&path, &content, &content, 0, &cancelled) {
+//!     Paragraph::new(text).render(area, buf);
+//! }
+//! ```
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+};
+
+use ratatui::{
+    style::Style,
+    text::{Line, Span, Text},
+};
+use regex::Regex;
+use syntect::{
+    highlighting::ScopeSelectors,
+    parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet},
+    util::LinesWithEndings,
+};
+
+use crate::theme::Theme;
+
+/// modelines are only looked for in this many lines at the start and end of the file
+const MODELINE_LINES: usize = 5;
+
+/// lines longer than this, like the ones of minified files, are cut, they're wider than any pane
+/// and take too long to highlight. the file is shown without colors from the first one on
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// tabs are shown as this many spaces, the terminal would otherwise jump to the next tab stop
+const TAB_WIDTH: usize = 4;
+
+/// the grammars take a while to load, so they're loaded once, the first time they're needed
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static STYLES: OnceLock<Vec<(ScopeSelectors, Style)>> = OnceLock::new();
+
+pub struct Highlighter {}
+
+impl Highlighter {
+    /// the lines of [content](str), the text of the file at [path](Path) from line
+    /// [first_line](usize) (starting at 0) on, highlighted and numbered. the language is found
+    /// with [head](str), the start of the file
+    /// returns `None` if [cancelled](AtomicBool) was set before it was done
+    ///
+    /// content not starting at the top is highlighted as if it did, which may get the first
+    /// lines wrong, like the ones in the middle of a comment
+    pub fn highlight(
+        path: &Path,
+        content: &str,
+        head: &str,
+        first_line: usize,
+        cancelled: &AtomicBool,
+    ) -> Option<Text<'static>> {
+        let theme = Theme::default();
+        let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+        let syntax = Highlighter::find_syntax(syntaxes, path, head);
+
+        let gutter_width = (first_line + content.lines().count())
+            .max(1)
+            .to_string()
+            .len();
+
+        let mut parser = ParseState::new(syntax);
+        let mut scopes = ScopeStack::new();
+        // if the grammar fails on a line the rest of the file is shown without colors
+        let mut highlighting = true;
+
+        let mut lines: Vec<Line> = Vec::new();
+        for (index, line) in LinesWithEndings::from(content).enumerate() {
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let mut spans = vec![Span::styled(
+                format!("{:>gutter_width$} ", first_line + index + 1),
+                theme.line_number,
+            )];
+
+            let line = match line.char_indices().nth(MAX_LINE_LENGTH) {
+                Some((cut, _)) => {
+                    highlighting = false;
+                    &line[..cut]
+                }
+                None => line,
+            };
+            let operations = match highlighting {
+                true => parser.parse_line(line, syntaxes).unwrap_or_else(|_| {
+                    highlighting = false;
+                    Vec::new()
+                }),
+                false => Vec::new(),
+            };
+
+            // each operation changes the scopes from where it is in the line onwards
+            let mut start = 0;
+            for (end, operation) in operations {
+                Highlighter::push_span(&mut spans, &line[start..end], &scopes);
+                if scopes.apply(&operation).is_err() {
+                    highlighting = false;
+                }
+                start = end;
+            }
+            Highlighter::push_span(&mut spans, &line[start..], &scopes);
+
+            lines.push(Line::from(spans));
+        }
+
+        Some(Text::from(lines))
+    }
+
+    fn push_span(spans: &mut Vec<Span<'static>>, text: &str, scopes: &ScopeStack) {
+        let text = text
+            .trim_end_matches(['\n', '\r'])
+            .replace('\t', &" ".repeat(TAB_WIDTH));
+        if !text.is_empty() {
+            spans.push(Span::styled(text, Highlighter::get_style(scopes)));
+        }
+    }
+
+    /// the style of the selector in [Theme] that matches [scopes](ScopeStack) most specifically
+    fn get_style(scopes: &ScopeStack) -> Style {
+        let styles = STYLES.get_or_init(|| {
+            Theme::default()
+                .code
+                .into_iter()
+                .filter_map(|(selector, style)| Some((selector.parse().ok()?, style)))
+                .collect()
+        });
+
+        styles
+            .iter()
+            .filter_map(|(selectors, style)| {
+                Some((selectors.does_match(scopes.as_slice())?, *style))
+            })
+            .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, style)| style)
+            .unwrap_or_default()
+    }
+
+    fn find_syntax<'a>(syntaxes: &'a SyntaxSet, path: &Path, content: &str) -> &'a SyntaxReference {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let first_line = content.lines().next().unwrap_or_default();
+
+        Highlighter::find_modeline(content)
+            .and_then(|language| syntaxes.find_syntax_by_token(&language))
+            // names like Makefile or .bashrc are listed as extensions by the grammars
+            .or_else(|| syntaxes.find_syntax_by_extension(&file_name))
+            .or_else(|| syntaxes.find_syntax_by_extension(&extension))
+            .or_else(|| syntaxes.find_syntax_by_first_line(first_line))
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+    }
+
+    /// the language set by a modeline near the start or the end of [content](str), like
+    /// `vim: set ft=python:` or `-*- mode: python -*-`
+    fn find_modeline(content: &str) -> Option<String> {
+        let vim = Regex::new(r"\b(?:vi|vim|ex):.*\b(?:ft|filetype|syntax)=([\w+#-]+)").ok()?;
+        let emacs = Regex::new(r"-\*-\s*(?:.*?\bmode:\s*)?([\w+#-]+)[\s;]").ok()?;
+
+        let lines: Vec<&str> = content.lines().collect();
+        let end = lines
+            .len()
+            .saturating_sub(MODELINE_LINES)
+            .max(MODELINE_LINES);
+
+        lines
+            .iter()
+            .take(MODELINE_LINES)
+            .chain(lines.iter().skip(end))
+            .find_map(|line| {
+                vim.captures(line)
+                    .or_else(|| emacs.captures(line))
+                    .map(|captures| captures[1].to_string())
+            })
+    }
+}
@@ -1 +1,7 @@
+pub mod binary;
+pub mod fs_watcher;
+pub mod highlighter;
+pub mod preview;
+pub mod preview_cache;
+#[allow(clippy::module_inception)]
 pub mod preview_pane;
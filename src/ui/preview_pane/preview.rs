@@ -0,0 +1,618 @@
+//! Generates the previews of files on background tasks
+//!
+//! Reading a big file or turning an image into characters takes long enough to make navigation
+//! stutter, so previews are generated on a blocking task while the preview pane shows a
+//! placeholder. A task that is no longer needed, because the cursor moved on, is cancelled.
+//!
+//! Text files are read a window of lines at a time, so a huge file doesn't have to be read, or
+//! kept in memory, entirely. Only the lines before the window are read, to find where it starts,
+//! and where the windows found start is kept for each file, so a window further down only reads
+//! on from the last one found.
+//! Binary files are shown as a hex dump instead, read a window of lines of the dump at a time.
+//!
+//! # Example
+//! ```rust
+//! let mut task = Preview::spawn(PreviewKey::new(path, 80, 24, 0));
+//!
+//! // later, without blocking
+//! if let Ok(preview) = task.receiver.try_recv() {
+//!     // show it
+//! }
+//! // or, if the cursor moved on
+//! task.cancel();
+//! ```
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    mem::size_of,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::SystemTime,
+};
+
+use lru::LruCache;
+use rascii_art::RenderOptions;
+use ratatui::text::{Line, Span, Text};
+use tokio::sync::oneshot::{self, Receiver};
+
+use crate::ui::file_picker::dir::Dir;
+
+use super::{
+    binary::{Binary, BINARY_CHECK_SIZE, BYTES_PER_LINE},
+    highlighter::Highlighter,
+};
+
+/// files are read this much at a time, checking if the task was cancelled in between
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// a window of a text file is cut after this many bytes, even if it has fewer lines
+const MAX_WINDOW_BYTES: usize = 1024 * 1024;
+
+/// text files are read this many lines at a time
+pub const WINDOW_LINES: usize = 1000;
+
+/// the lines after a window that are read along with it, so a pane up to this tall never shows
+/// two windows at once
+const WINDOW_OVERLAP: usize = 256;
+
+/// asks for the window with the last lines of the file, whose number isn't known before reading it
+pub const LAST_WINDOW: usize = usize::MAX;
+
+/// how many files the starts of their windows are kept for
+const MAX_INDEXED_FILES: usize = 64;
+
+/// a file as it is now, by its path, modification time and size
+type FileVersion = (PathBuf, Option<SystemTime>, u64);
+
+/// where the windows of the files read recently start
+static WINDOW_INDEXES: OnceLock<Mutex<LruCache<FileVersion, WindowIndex>>> = OnceLock::new();
+
+/// where the windows of a text file found so far start
+///
+/// [starts](Vec<u64>): where each window found starts in bytes, the first one at 0
+/// [end](Option<(usize, u64)>): the number of lines of the file and where it ends, once it was read
+/// to the end
+#[derive(Clone)]
+struct WindowIndex {
+    starts: Vec<u64>,
+    end: Option<(usize, u64)>,
+}
+
+/// what a preview is generated from, a preview is stale once any of it changes
+///
+/// [path](PathBuf): the entry previewed
+/// [modified](Option<SystemTime>): when the entry was last modified, if it could be read
+/// [size](u64): size of the entry in bytes
+/// [width](u16): width of the area the preview is shown in
+/// [height](u16): height of the area the preview is shown in
+/// [window](usize): which window of [WINDOW_LINES] lines of a text file, or [LAST_WINDOW]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PreviewKey {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+    pub width: u16,
+    pub height: u16,
+    pub window: usize,
+}
+
+impl PreviewKey {
+    /// the key to preview [window](usize) of [path](PathBuf) as it is now, in an area of
+    /// [width](u16) by [height](u16)
+    pub fn new(path: PathBuf, width: u16, height: u16, window: usize) -> PreviewKey {
+        let metadata = path.metadata().ok();
+        PreviewKey {
+            modified: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok()),
+            size: metadata.map(|metadata| metadata.len()).unwrap_or_default(),
+            path,
+            width,
+            height,
+            window,
+        }
+    }
+
+    /// `true` if both keys are for the same entry, as it is now, in the same area, no matter the
+    /// window
+    pub fn is_same_entry(&self, other: &PreviewKey) -> bool {
+        self.path == other.path
+            && self.modified == other.modified
+            && self.size == other.size
+            && self.width == other.width
+            && self.height == other.height
+    }
+}
+
+/// some lines of a preview
+///
+/// [text](Text): the lines
+/// [first_line](usize): how many lines of the preview come before [text](Text)
+/// [offsets](Vec<u64>): where each line starts in the file, in bytes. empty if the lines don't
+/// map to bytes of the file, like the ones of an image
+/// [total_lines](Option<usize>): how many lines the whole preview has, if it's known
+/// [mode](ContentMode): what the lines are
+pub struct Content {
+    pub text: Text<'static>,
+    pub first_line: usize,
+    pub offsets: Vec<u64>,
+    pub total_lines: Option<usize>,
+    pub mode: ContentMode,
+}
+
+pub enum ContentMode {
+    /// the lines of a text file, [truncated](bool) if the file goes on past a line too long to
+    /// be read
+    Text { truncated: bool },
+    /// the hex dump of a binary file, of type [file_type](str)
+    Hex { file_type: &'static str },
+    /// an image turned into characters
+    Image,
+}
+
+impl Content {
+    /// `true` if the lines from [scroll](usize) to [scroll](usize) + [height](usize) are all
+    /// here, or the ones missing are past the end
+    pub fn covers(&self, scroll: usize, height: usize) -> bool {
+        let last_line = self.first_line + self.text.lines.len();
+        scroll >= self.first_line
+            && (scroll + height <= last_line || self.total_lines == Some(last_line))
+    }
+}
+
+pub enum Preview {
+    /// the lines of a text file, or of an image turned into characters
+    Content(Content),
+    /// the entries of a directory, as returned by [Dir::get_dir_entries_ordered], shown in the
+    /// nested file picker of the preview pane so the user can browse them
+    Listing(Vec<Dir>),
+    /// why there's nothing to show
+    Message(String),
+}
+
+/// a preview being generated
+///
+/// [key](PreviewKey): what the preview is for
+/// [cancelled](AtomicBool): tells the task to stop
+/// [receiver](Receiver<Preview>): where the preview arrives once it's done
+pub struct PreviewTask {
+    pub key: PreviewKey,
+    cancelled: Arc<AtomicBool>,
+    pub receiver: Receiver<Preview>,
+}
+
+impl PreviewTask {
+    /// stops the task as soon as it can, nothing will be received from it
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Preview {
+    /// starts generating the preview for [key](PreviewKey) on a blocking task
+    pub fn spawn(key: PreviewKey) -> PreviewTask {
+        let (sender, receiver) = oneshot::channel::<Preview>();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let task_key = key.clone();
+        let task_cancelled = cancelled.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Some(preview) = Preview::generate(&task_key, &task_cancelled) {
+                // the pane may not be waiting for it anymore
+                let _ = sender.send(preview);
+            }
+        });
+
+        PreviewTask {
+            key,
+            cancelled,
+            receiver,
+        }
+    }
+
+    /// roughly how many bytes of memory the preview takes
+    pub fn get_size(&self) -> usize {
+        match self {
+            Preview::Content(content) => {
+                content
+                    .text
+                    .lines
+                    .iter()
+                    .flat_map(|line| line.spans.iter())
+                    .map(|span| size_of::<Span>() + span.content.len())
+                    .sum::<usize>()
+                    + content.text.lines.len() * size_of::<Line>()
+                    + content.offsets.len() * size_of::<u64>()
+            }
+            Preview::Listing(entries) => entries
+                .iter()
+                .map(|entry| {
+                    size_of::<Dir>() + entry.pathbuf.as_os_str().len() + entry.display_name.len()
+                })
+                .sum(),
+            Preview::Message(message) => message.len(),
+        }
+    }
+
+    /// returns `None` if [cancelled](AtomicBool) was set before the preview was done
+    fn generate(key: &PreviewKey, cancelled: &AtomicBool) -> Option<Preview> {
+        if key.path.is_dir() {
+            return Some(match Dir::get_dir_entries_ordered(key.path.clone()) {
+                Ok(entries) => Preview::Listing(entries),
+                Err(e) => Preview::Message(e.to_string()),
+            });
+        }
+
+        let extension = key
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("png") | Some("jpg") | Some("jpeg") => Preview::render_image(key),
+            _ => Preview::read_text(key, cancelled),
+        }
+    }
+
+    /// the image takes the whole width, keeping its proportions, so tall images are scrolled
+    /// through. images are small enough to be rendered whole, the window is ignored
+    fn render_image(key: &PreviewKey) -> Option<Preview> {
+        let mut buffer = String::new();
+
+        let preview = match rascii_art::render_to(
+            key.path.to_string_lossy().as_ref(),
+            &mut buffer,
+            &RenderOptions::new()
+                .width(key.width as u32)
+                .colored(false)
+                .charset(&[" ", "🞗", "🞘", "🞙", "🞚", "◈", "🞛", "❖", "⯁", "■"]),
+        ) {
+            Ok(()) => {
+                let lines: Vec<Line> = buffer
+                    .lines()
+                    .map(|line| Line::raw(line.to_string()))
+                    .collect();
+                Preview::Content(Content {
+                    first_line: 0,
+                    offsets: Vec::new(),
+                    total_lines: Some(lines.len()),
+                    text: Text::from(lines),
+                    mode: ContentMode::Image,
+                })
+            }
+            Err(_) => Preview::Message("Could not render image".to_string()),
+        };
+
+        Some(preview)
+    }
+
+    fn read_text(key: &PreviewKey, cancelled: &AtomicBool) -> Option<Preview> {
+        let could_not_read = || Some(Preview::Message("Could not read file".to_string()));
+
+        let Ok(mut file) = File::open(&key.path) else {
+            return could_not_read();
+        };
+
+        // the start of the file tells if it's text, and in what language
+        let mut head = Vec::new();
+        if (&mut file)
+            .take(BINARY_CHECK_SIZE as u64)
+            .read_to_end(&mut head)
+            .is_err()
+        {
+            return could_not_read();
+        }
+        if Binary::is_binary(&head) {
+            return Preview::read_hex(key, &mut file, Binary::get_file_type(&head));
+        }
+
+        // finds where the window starts, reading the lines before it
+        if file.rewind().is_err() {
+            return could_not_read();
+        }
+        let (window, start) = match Preview::find_window(&mut file, key, cancelled)? {
+            Ok(found) => found,
+            Err(_) => return could_not_read(),
+        };
+        let first_line = window * WINDOW_LINES;
+        if file.seek(SeekFrom::Start(start)).is_err() {
+            return could_not_read();
+        }
+
+        // reads the lines of the window, and where each starts, up to a limit, a file could be
+        // one huge line
+        let mut bytes = Vec::new();
+        let mut offsets = vec![start];
+        let mut chunk = vec![0; READ_CHUNK_SIZE];
+        let mut reached_end = false;
+        let mut truncated = false;
+        'reading: loop {
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            let read = match file.read(&mut chunk) {
+                Ok(0) => {
+                    reached_end = true;
+                    break;
+                }
+                Ok(read) => read,
+                Err(_) => return could_not_read(),
+            };
+            for (index, byte) in chunk[..read].iter().enumerate() {
+                if *byte != b'\n' {
+                    continue;
+                }
+                offsets.push(start + (bytes.len() + index) as u64 + 1);
+                if offsets.len() > WINDOW_LINES + WINDOW_OVERLAP {
+                    bytes.extend_from_slice(&chunk[..=index]);
+                    break 'reading;
+                }
+            }
+            bytes.extend_from_slice(&chunk[..read]);
+
+            if bytes.len() >= MAX_WINDOW_BYTES {
+                bytes.truncate(MAX_WINDOW_BYTES);
+                offsets.retain(|offset| *offset <= start + bytes.len() as u64);
+                truncated = true;
+                break;
+            }
+        }
+        // the last offset is where the line after the window starts, if there's one
+        if offsets.last() == Some(&(start + bytes.len() as u64)) {
+            offsets.pop();
+        }
+
+        // a few bytes that aren't UTF-8 are shown as replacement characters
+        let content = String::from_utf8_lossy(&bytes);
+        let head = match window {
+            0 => content.clone(),
+            _ => String::from_utf8_lossy(&head),
+        };
+        let text = Highlighter::highlight(&key.path, &content, &head, first_line, cancelled)?;
+
+        Some(Preview::Content(Content {
+            // past where it was cut the file isn't shown, as if it ended there
+            total_lines: (reached_end || truncated).then_some(first_line + text.lines.len()),
+            text,
+            first_line,
+            offsets,
+            mode: ContentMode::Text { truncated },
+        }))
+    }
+
+    /// the hex dump of the window of [file](File), a binary file of type [file_type](str). each
+    /// line of the dump is a line of the window
+    fn read_hex(key: &PreviewKey, file: &mut File, file_type: &'static str) -> Option<Preview> {
+        let total_lines = (key.size as usize).div_ceil(BYTES_PER_LINE);
+        let window = match key.window {
+            // the last window has at least as many lines as fit in the pane
+            LAST_WINDOW => total_lines.saturating_sub(WINDOW_OVERLAP) / WINDOW_LINES,
+            window => window,
+        };
+        let first_line = window * WINDOW_LINES;
+        let start = (first_line * BYTES_PER_LINE) as u64;
+
+        // the bytes of a line are always in the same place, there's nothing to find
+        let mut bytes = Vec::new();
+        if file.seek(SeekFrom::Start(start)).is_err()
+            || file
+                .take(((WINDOW_LINES + WINDOW_OVERLAP) * BYTES_PER_LINE) as u64)
+                .read_to_end(&mut bytes)
+                .is_err()
+        {
+            return Some(Preview::Message("Could not read file".to_string()));
+        }
+
+        let text = Text::from(Binary::get_hex_lines(&bytes, start));
+        Some(Preview::Content(Content {
+            offsets: (0..text.lines.len())
+                .map(|line| start + (line * BYTES_PER_LINE) as u64)
+                .collect(),
+            text,
+            first_line,
+            total_lines: Some(total_lines),
+            mode: ContentMode::Hex { file_type },
+        }))
+    }
+
+    /// reads [file](File) up to the start of the window of [key](PreviewKey), returning its
+    /// number, which is only known once read for [LAST_WINDOW], and where it starts in bytes.
+    /// reading carries on from the last window of the file found before
+    /// returns `None` if [cancelled](AtomicBool) was set before it was found
+    fn find_window(
+        file: &mut File,
+        key: &PreviewKey,
+        cancelled: &AtomicBool,
+    ) -> Option<std::io::Result<(usize, u64)>> {
+        let window = key.window;
+        let indexes = WINDOW_INDEXES.get_or_init(|| {
+            Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_INDEXED_FILES).unwrap_or(NonZeroUsize::MIN),
+            ))
+        });
+        let index_key = (key.path.clone(), key.modified, key.size);
+        let mut index = indexes
+            .lock()
+            .ok()
+            .and_then(|mut indexes| indexes.get(&index_key).cloned())
+            .unwrap_or(WindowIndex {
+                starts: vec![0],
+                end: None,
+            });
+
+        if index.end.is_none() && (window == LAST_WINDOW || index.starts.len() <= window) {
+            // a window starts right after a new line, and after as many lines as the ones before it
+            let mut offset = *index.starts.last().unwrap_or(&0);
+            let mut lines = (index.starts.len() - 1) * WINDOW_LINES;
+            let mut last_byte = b'\n';
+            if let Err(error) = file.seek(SeekFrom::Start(offset)) {
+                return Some(Err(error));
+            }
+
+            let mut chunk = vec![0; READ_CHUNK_SIZE];
+            while window == LAST_WINDOW || index.starts.len() <= window {
+                if cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                let read = match file.read(&mut chunk) {
+                    Ok(0) => {
+                        // the last line may not end in a new line
+                        if last_byte != b'\n' {
+                            lines += 1;
+                        }
+                        index.end = Some((lines, offset));
+                        break;
+                    }
+                    Ok(read) => read,
+                    Err(error) => return Some(Err(error)),
+                };
+                for (position, byte) in chunk[..read].iter().enumerate() {
+                    if *byte == b'\n' {
+                        lines += 1;
+                        if lines.is_multiple_of(WINDOW_LINES) {
+                            index.starts.push(offset + position as u64 + 1);
+                        }
+                    }
+                }
+                offset += read as u64;
+                last_byte = chunk[read - 1];
+            }
+
+            // the windows found are kept even if the task was cancelled
+            if let Ok(mut indexes) = indexes.lock() {
+                indexes.put(index_key, index.clone());
+            }
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+        }
+
+        let (lines, end) = index.end.unwrap_or_default();
+        if window != LAST_WINDOW {
+            // a window past the end starts at the end, it's empty
+            return Some(Ok((window, *index.starts.get(window).unwrap_or(&end))));
+        }
+
+        // the last window has at least as many lines as fit in the pane
+        let last = lines.saturating_sub(WINDOW_OVERLAP) / WINDOW_LINES;
+        Some(Ok((last, index.starts[last])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::ui::file_picker::test_utils::TestDir;
+
+    /// every line is 5 bytes long, so window `n` starts at `n * WINDOW_LINES * 5`
+    fn write_lines(dir: &TestDir, name: &str, count: usize) -> PathBuf {
+        let contents: String = (0..count).map(|line| format!("{:04}\n", line)).collect();
+        dir.write(name, &contents)
+    }
+
+    fn find_window(path: &Path, window: usize) -> Option<(usize, u64)> {
+        let mut file = File::open(path).unwrap();
+        let key = PreviewKey::new(path.to_path_buf(), 80, 24, window);
+        Preview::find_window(&mut file, &key, &AtomicBool::new(false)).map(|res| res.unwrap())
+    }
+
+    fn get_index(path: &Path) -> Option<WindowIndex> {
+        let key = PreviewKey::new(path.to_path_buf(), 80, 24, 0);
+        WINDOW_INDEXES
+            .get()?
+            .lock()
+            .ok()?
+            .peek(&(key.path, key.modified, key.size))
+            .cloned()
+    }
+
+    #[test]
+    fn windows_start_every_few_lines() {
+        let dir = TestDir::new("window-starts");
+        let path = write_lines(&dir, "lines.txt", 2500);
+
+        assert_eq!(find_window(&path, 0), Some((0, 0)));
+        assert_eq!(
+            find_window(&path, 2),
+            Some((2, 2 * WINDOW_LINES as u64 * 5))
+        );
+        assert_eq!(find_window(&path, 1), Some((1, WINDOW_LINES as u64 * 5)));
+        // a window past the end starts at the end
+        assert_eq!(find_window(&path, 5), Some((5, 2500 * 5)));
+    }
+
+    #[test]
+    fn windows_found_are_kept_for_the_next_reads() {
+        let dir = TestDir::new("window-index");
+        let path = write_lines(&dir, "lines.txt", 2500);
+
+        find_window(&path, 1);
+        let index = get_index(&path).unwrap();
+        // the file is read in chunks, windows after the one asked for may be found with it
+        assert!(index.starts.starts_with(&[0, WINDOW_LINES as u64 * 5]));
+        assert!(index.end.is_none());
+
+        find_window(&path, LAST_WINDOW);
+        let index = get_index(&path).unwrap();
+        assert_eq!(index.starts.len(), 3);
+        assert_eq!(index.end, Some((2500, 2500 * 5)));
+
+        // the file changed, what was found for it before doesn't apply anymore
+        let path = write_lines(&dir, "lines.txt", 10);
+        assert!(get_index(&path).is_none());
+        assert_eq!(find_window(&path, 1), Some((1, 10 * 5)));
+    }
+
+    #[test]
+    fn last_window_fits_a_whole_pane() {
+        let dir = TestDir::new("window-last");
+
+        let path = write_lines(&dir, "long.txt", 2500);
+        assert_eq!(
+            find_window(&path, LAST_WINDOW),
+            Some((2, 2 * WINDOW_LINES as u64 * 5))
+        );
+
+        // the last window would only have a few lines, the one before is shown instead
+        let path = write_lines(&dir, "short-end.txt", 2 * WINDOW_LINES + 10);
+        assert_eq!(
+            find_window(&path, LAST_WINDOW),
+            Some((1, WINDOW_LINES as u64 * 5))
+        );
+
+        let path = dir.write("empty.txt", "");
+        assert_eq!(find_window(&path, LAST_WINDOW), Some((0, 0)));
+    }
+
+    #[test]
+    fn last_line_counts_without_a_line_break() {
+        let dir = TestDir::new("window-no-break");
+        let path = dir.write("no-break.txt", "a\nb");
+
+        find_window(&path, LAST_WINDOW);
+
+        assert_eq!(get_index(&path).unwrap().end, Some((2, 3)));
+    }
+
+    #[test]
+    fn cancelled_search_returns_nothing() {
+        let dir = TestDir::new("window-cancelled");
+        let path = write_lines(&dir, "lines.txt", 2500);
+        let key = PreviewKey::new(path.clone(), 80, 24, 2);
+
+        let res = Preview::find_window(
+            &mut File::open(&path).unwrap(),
+            &key,
+            &AtomicBool::new(true),
+        );
+
+        assert!(res.is_none());
+    }
+}
@@ -0,0 +1,93 @@
+//! Keeps the previews generated recently, so going back to an entry shows it right away
+//!
+//! Previews are kept by [PreviewKey], so a preview is not used anymore once its entry is modified
+//! or the pane is resized. The least recently used previews are dropped once the cache takes more
+//! than [MAX_MEMORY].
+//!
+//! # Example
+//! ```rust
+//! let mut cache = PreviewCache::default();
+//! let key = PreviewKey::new(path, 80, 24, 0);
+//!
+//! let preview = match cache.get(&key) {
+//!     Some(preview) => preview,
+//!     None => {
+//!         let preview = Arc::new(generate_preview(&key));
+//!         cache.insert(key, preview.clone());
+//!         preview
+//!     }
+//! };
+//! ```
+
+use std::{path::Path, sync::Arc};
+
+use lru::LruCache;
+
+use super::preview::{Preview, PreviewKey};
+
+/// roughly how much memory the cached previews can take, in bytes
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// [previews](LruCache<PreviewKey, Arc<Preview>>): the previews, most recently used first
+/// [memory](usize): roughly how much memory the previews take, in bytes
+pub struct PreviewCache {
+    previews: LruCache<PreviewKey, Arc<Preview>>,
+    memory: usize,
+}
+
+impl Default for PreviewCache {
+    fn default() -> Self {
+        PreviewCache {
+            previews: LruCache::unbounded(),
+            memory: 0,
+        }
+    }
+}
+
+impl PreviewCache {
+    /// returns the preview for [key](PreviewKey), making it the most recently used
+    pub fn get(&mut self, key: &PreviewKey) -> Option<Arc<Preview>> {
+        self.previews.get(key).cloned()
+    }
+
+    /// adds [preview](Preview), dropping the least recently used previews if there's no room
+    /// previews bigger than the whole cache are not kept
+    pub fn insert(&mut self, key: PreviewKey, preview: Arc<Preview>) {
+        let size = preview.get_size();
+        if size > MAX_MEMORY {
+            return;
+        }
+
+        if let Some(old) = self.previews.put(key, preview) {
+            self.memory -= old.get_size();
+        }
+        self.memory += size;
+
+        while self.memory > MAX_MEMORY {
+            match self.previews.pop_lru() {
+                Some((_, dropped)) => self.memory -= dropped.get_size(),
+                None => break,
+            }
+        }
+    }
+
+    /// drops the previews of [path](Path) and, as its listing changed too, of the directory it's
+    /// in. returns `true` if any was dropped
+    pub fn invalidate(&mut self, path: &Path) -> bool {
+        let stale: Vec<PreviewKey> = self
+            .previews
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| key.path == path || path.parent() == Some(key.path.as_path()))
+            .cloned()
+            .collect();
+
+        for key in stale.iter() {
+            if let Some(dropped) = self.previews.pop(key) {
+                self.memory -= dropped.get_size();
+            }
+        }
+
+        !stale.is_empty()
+    }
+}
@@ -1,5 +1,4 @@
-use crossterm::event::KeyEvent;
-use rascii_art::RenderOptions;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -8,91 +7,435 @@ use ratatui::{
     widgets::{Block, Paragraph, Widget},
 };
 
+/// [curr_entry](PathBuf): the entry being previewed
+/// [scroll](usize): how many lines of the preview are scrolled past, `usize::MAX` for the end
+/// of the file until its number of lines is known
+/// [total_lines](Option<usize>): how many lines the preview of the entry has, once known
+/// [view_height](usize): how many lines fit in the pane
+/// [preview](Option<(PreviewKey, Arc<Preview>)>): the last preview shown and what it's for
+/// [task](Option<PreviewTask>): the preview being generated in the background
+/// [cache](PreviewCache): previews generated recently
+/// [watcher](FsWatcher): reports changes to the entry previewed, to update it
+/// [listing](Option<Selection>): what to select in the nested picker once the listing of the
+/// directory being read arrives
 pub struct PreviewPane {
     curr_entry: PathBuf,
+    scroll: usize,
+    total_lines: Option<usize>,
+    view_height: usize,
+    preview: Option<(PreviewKey, Arc<Preview>)>,
+    task: Option<PreviewTask>,
+    cache: PreviewCache,
+    watcher: FsWatcher,
+    listing: Option<Selection>,
     pub active: bool,
     pub needs_redraw: bool,
     file_picker: FilePicker,
 }
-use std::path::PathBuf;
+use std::{io::Error, path::PathBuf, sync::Arc};
 
-use crate::ui::file_picker::{dir::Dir, file_picker::FilePicker};
+use tokio::sync::oneshot::error::TryRecvError;
+
+use crate::ui::{
+    file_picker::{
+        dir::Dir,
+        file_picker::{FilePicker, Selection},
+        jobs::{JobReport, Jobs},
+    },
+    utils::Utils,
+};
+
+use super::{
+    fs_watcher::FsWatcher,
+    preview::{Content, ContentMode, Preview, PreviewKey, PreviewTask, LAST_WINDOW, WINDOW_LINES},
+    preview_cache::PreviewCache,
+};
 
 impl PreviewPane {
-    pub fn new() -> PreviewPane {
+    pub fn new(jobs: Jobs) -> PreviewPane {
         PreviewPane {
             curr_entry: PathBuf::new(),
+            scroll: 0,
+            total_lines: None,
+            view_height: 0,
+            preview: None,
+            task: None,
+            cache: PreviewCache::default(),
+            watcher: FsWatcher::new(),
+            listing: None,
             active: false,
             needs_redraw: false,
-            file_picker: FilePicker::new(false),
+            file_picker: FilePicker::new(false, jobs),
         }
     }
 
-    pub fn initialize(&mut self, dir: Option<PathBuf>) {
-        match dir.clone() {
-            Some(value) => {
-                self.curr_entry = value;
-                if self.curr_entry.is_dir() {
-                    self.file_picker.initialize(dir, Some(0));
-                }
+    /// previews the entry at [path](PathBuf)
+    pub fn initialize(&mut self, path: PathBuf) {
+        self.scroll = 0;
+        self.total_lines = None;
+        self.curr_entry = path.clone();
+        self.listing = None;
+        // the preview of the entry left is not needed anymore
+        if self.task.as_ref().is_some_and(|task| task.key.path != path) {
+            self.cancel_task();
+        }
+        if self.curr_entry.is_dir() {
+            self.show_listing(Selection::Index(0));
+        }
+
+        // changes to the entry are seen in the directory it's in, a directory also needs its own
+        // entries watched for the listing
+        let mut watched: Vec<PathBuf> = path.parent().map(PathBuf::from).into_iter().collect();
+        if path.is_dir() {
+            watched.push(path);
+        }
+        self.watcher.watch(watched);
+    }
+    /// previews the file at [path](PathBuf) scrolled so [line](usize) (starting at 1) is near the
+    /// top, with a few lines before it for context
+    pub fn initialize_at_line(&mut self, path: PathBuf, line: usize) {
+        self.initialize(path);
+        self.scroll = line.saturating_sub(4);
+    }
+
+    /// lists the previewed directory in the nested file picker with [selection](Selection) under
+    /// the cursor. a cached listing is shown right away, otherwise it's read on a background task
+    /// and shown once it arrives
+    fn show_listing(&mut self, selection: Selection) {
+        let key = PreviewKey::new(self.curr_entry.clone(), 0, 0, 0);
+
+        match self.cache.get(&key) {
+            Some(preview) => self.apply_listing(key.path, &preview, selection),
+            None => {
+                self.listing = Some(selection);
+                self.request_preview(key);
             }
-            None => self.curr_entry = Dir::get_cur_dir().pathbuf,
         }
     }
+
+    /// puts the entries of the listing [preview](Preview) of [dir](PathBuf) in the nested file
+    /// picker, or why it couldn't be read
+    fn apply_listing(&mut self, dir: PathBuf, preview: &Preview, selection: Selection) {
+        let entries = match preview {
+            Preview::Listing(entries) => Ok(entries.clone()),
+            Preview::Message(message) => Err(Error::other(message.clone())),
+            Preview::Content(_) => Err(Error::other("not a directory")),
+        };
+        self.listing = None;
+        self.file_picker
+            .initialize_with_entries(dir, entries, Some(selection));
+    }
+
+    /// `true` while the nested picker still shows another directory than the one being listed
+    fn is_listing(&self) -> bool {
+        self.listing.is_some() && self.file_picker.get_curr_displaying_dir() != self.curr_entry
+    }
+
+    /// drops the previews of the entries that changed on disk, reloading the one being shown
+    /// returns `true` if the pane needs to be drawn again
+    pub fn handle_fs_changes(&mut self) -> bool {
+        let mut needs_redraw = false;
+
+        for path in self.watcher.take_changes() {
+            self.cache.invalidate(&path);
+
+            let shown = self
+                .preview
+                .as_ref()
+                .is_some_and(|(key, _)| key.path == path);
+            if shown {
+                self.preview = None;
+                self.total_lines = None;
+                needs_redraw = true;
+            }
+
+            // the nested picker may have moved to another directory, it's only reloaded if it
+            // still shows the one previewed
+            let listed = self.curr_entry.is_dir()
+                && self.file_picker.get_curr_displaying_dir() == self.curr_entry
+                && (path == self.curr_entry || path.parent() == Some(self.curr_entry.as_path()));
+            if listed {
+                let selection = Selection::Entry(self.file_picker.curr_sel_entry());
+                self.show_listing(selection);
+                needs_redraw = true;
+            }
+        }
+
+        needs_redraw
+    }
+
+    /// `true` while the user is typing in the search box of the previewed directory, so every key
+    /// should be sent here
+    pub fn is_typing(&self) -> bool {
+        self.curr_entry.is_dir() && self.file_picker.is_typing()
+    }
+
+    /// refreshes the previewed directory after a background job changed the file system
+    pub fn job_finished(&mut self, report: &JobReport) {
+        if self.curr_entry.is_dir() {
+            self.file_picker.job_finished(report);
+        }
+    }
+
     pub fn handle_keys(&mut self, key: KeyEvent) {
         if self.curr_entry.is_dir() {
+            if self.is_listing() {
+                return;
+            }
             self.file_picker.handle_keys(key);
+            // a listing still being read mustn't bring the user back once they browsed away
+            if self.file_picker.get_curr_displaying_dir() != self.curr_entry {
+                self.listing = None;
+            }
+            return;
+        }
+
+        // only the contents of files are scrolled, not messages
+        if !self
+            .preview
+            .as_ref()
+            .is_some_and(|(_, preview)| matches!(preview.as_ref(), Preview::Content(_)))
+        {
+            return;
+        }
+
+        self.clamp_scroll();
+        let half_page = (self.view_height / 2).max(1);
+        match key.code {
+            // [Ctrl+d/u] scroll half a page down/up
+            KeyCode::Char('d') if key.modifiers == KeyModifiers::CONTROL => {
+                self.scroll = self.scroll.saturating_add(half_page)
+            }
+            KeyCode::Char('u') if key.modifiers == KeyModifiers::CONTROL => {
+                self.scroll = self.scroll.saturating_sub(half_page)
+            }
+            // [j/k] scroll a line down/up
+            KeyCode::Char('j') | KeyCode::Down => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            // [g/G] go to the start/end of the file
+            KeyCode::Char('g') => self.scroll = 0,
+            KeyCode::Char('G') => self.scroll = usize::MAX,
+            _ => (),
+        }
+        self.clamp_scroll();
+    }
+
+    /// keeps the last line of the file at the bottom of the pane at most, if it's known
+    fn clamp_scroll(&mut self) {
+        if let Some(total_lines) = self.total_lines {
+            self.scroll = self
+                .scroll
+                .min(total_lines.saturating_sub(self.view_height));
         }
     }
 
     pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
-        let preview_inner_area = self.render_preview_pane_block(area, buf);
+        let preview_inner_area = Block::bordered().inner(area);
 
-        // if its a directory shows another file picker
-        if self.curr_entry.is_dir() {
+        // the contents of a file are found first, the block shows how far they're scrolled
+        let file_view = match self.curr_entry.is_file() {
+            true => Some(self.get_file_view(preview_inner_area)),
+            false => None,
+        };
+        let position = file_view
+            .as_ref()
+            .and_then(|(_, position)| position.clone());
+
+        self.render_preview_pane_block(area, buf, position);
+
+        // if its a directory shows another file picker, once it's listed
+        if self.is_listing() {
+            Paragraph::new("loading…")
+                .style(Style::default().add_modifier(Modifier::DIM))
+                .render(preview_inner_area, buf);
+        } else if self.curr_entry.is_dir() {
             self.file_picker.active = self.active;
             self.file_picker.render(preview_inner_area, buf);
-        } else if self.curr_entry.is_file() {
-            match self.curr_entry.extension() {
-                Some(extension) => match extension.to_str() {
-                    Some("png") | Some("jpg") | Some("jpeg") => {
-                        let mut buffer = String::new();
-
-                        rascii_art::render_to(
-                            self.curr_entry.to_str().unwrap(),
-                            &mut buffer,
-                            &RenderOptions::new()
-                                .width(area.width as u32)
-                                .height(area.height as u32)
-                                .colored(false)
-                                .charset(&[" ", "🞗", "🞘", "🞙", "🞚", "◈", "🞛", "❖", "⯁", "■"]),
-                        )
-                        .unwrap();
-
-                        Paragraph::new(
-                            buffer
-                                .lines()
-                                .map(|line| Line::raw(line))
-                                .collect::<Vec<Line>>(),
-                        )
-                        .render(preview_inner_area, buf)
-                    }
-                    _ => {
-                        let content = std::fs::read_to_string(self.curr_entry.clone())
-                            .unwrap_or_else(|_| "Could not read file".to_string());
-                        Paragraph::new(content).render(preview_inner_area, buf)
-                    }
-                },
-                None => {
-                    let content = std::fs::read_to_string(self.curr_entry.clone())
-                        .unwrap_or_else(|_| "Could not read file".to_string());
-                    Paragraph::new(content).render(preview_inner_area, buf);
+        } else if let Some((view, _)) = file_view {
+            view.render(preview_inner_area, buf);
+        }
+    }
+
+    /// the lines of the file in view, or why they aren't, and where they are in the file
+    fn get_file_view(&mut self, area: Rect) -> (Paragraph<'static>, Option<String>) {
+        self.view_height = area.height as usize;
+        self.clamp_scroll();
+
+        let window = match self.scroll {
+            usize::MAX => LAST_WINDOW,
+            scroll => scroll / WINDOW_LINES,
+        };
+        let key = PreviewKey::new(self.curr_entry.clone(), area.width, area.height, window);
+
+        // a preview generated before is shown right away
+        if !self.is_showing(&key) {
+            if let Some(preview) = self.cache.get(&key) {
+                self.preview = Some((key.clone(), preview));
+            }
+        }
+
+        if !self.is_showing(&key) {
+            self.request_preview(key);
+            return (
+                Paragraph::new("loading…").style(Style::default().add_modifier(Modifier::DIM)),
+                None,
+            );
+        }
+
+        let Some((key, preview)) = self.preview.clone() else {
+            return (Paragraph::new(""), None);
+        };
+        match preview.as_ref() {
+            Preview::Content(content) => {
+                if content.total_lines.is_some() {
+                    self.total_lines = content.total_lines;
+                    self.clamp_scroll();
+                }
+                let skip = self.scroll.saturating_sub(content.first_line);
+
+                // only the lines in view are rendered, files can have a lot of them
+                let lines: Vec<Line> = content
+                    .text
+                    .lines
+                    .iter()
+                    .skip(skip)
+                    .take(self.view_height)
+                    .cloned()
+                    .collect();
+
+                (
+                    Paragraph::new(lines),
+                    Some(self.get_position(&key, content)),
+                )
+            }
+            Preview::Message(message) => (Paragraph::new(message.clone()), None),
+            // files are never listed
+            Preview::Listing(_) => (Paragraph::new(""), None),
+        }
+    }
+
+    /// `true` if the preview shown has the lines in view of the entry [key](PreviewKey) is for
+    fn is_showing(&self, key: &PreviewKey) -> bool {
+        let Some((shown_key, preview)) = self.preview.as_ref() else {
+            return false;
+        };
+        if !shown_key.is_same_entry(key) {
+            return false;
+        }
+        match preview.as_ref() {
+            Preview::Content(content) => {
+                // the end of the file was asked for, only the last window can show it
+                if self.scroll == usize::MAX {
+                    return content.total_lines.is_some() && shown_key.window == key.window;
+                }
+                content.covers(self.scroll, self.view_height)
+            }
+            _ => true,
+        }
+    }
+
+    /// where the top line in view is, like ` line 120/4000 · byte 6720 (35%) `. the byte offset is
+    /// left out for previews that don't map to the bytes of the file, like images. hex dumps show
+    /// the type of the file instead of the line
+    fn get_position(&self, key: &PreviewKey, content: &Content) -> String {
+        let scroll = self.scroll;
+        // windows before the last don't know, but it may have been read already
+        let total_lines = match self.total_lines {
+            Some(total_lines) => total_lines.to_string(),
+            None => "?".to_string(),
+        };
+        let mut position = match content.mode {
+            ContentMode::Hex { file_type } => {
+                " ".to_string() + file_type + ", " + &Utils::get_human_readable_size(key.size)
+            }
+            ContentMode::Text { truncated: true } => {
+                " line ".to_string() + &(scroll + 1).to_string() + "/" + &total_lines + " (cut)"
+            }
+            _ => " line ".to_string() + &(scroll + 1).to_string() + "/" + &total_lines,
+        };
+
+        if let Some(offset) = content
+            .offsets
+            .get(scroll.saturating_sub(content.first_line))
+        {
+            let percentage = match key.size {
+                0 => 100,
+                size => offset * 100 / size,
+            };
+            position =
+                position + " · byte " + &offset.to_string() + " (" + &percentage.to_string() + "%)";
+        }
+
+        position + " "
+    }
+
+    /// `true` while a preview is being generated
+    pub fn is_loading(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// takes the preview being generated if it's done
+    /// returns `true` if it was, so the pane needs to be drawn again
+    pub fn receive_preview(&mut self) -> bool {
+        let Some(task) = self.task.as_mut() else {
+            return false;
+        };
+
+        let preview = match task.receiver.try_recv() {
+            Ok(preview) => preview,
+            Err(TryRecvError::Empty) => return false,
+            // the task panicked
+            Err(TryRecvError::Closed) => Preview::Message("Could not read file".to_string()),
+        };
+
+        if let Some(task) = self.task.take() {
+            // listings go to the nested picker. only the ones read are cached, so a directory
+            // that couldn't be read is tried again next time
+            if let Some(selection) = self.listing.take() {
+                let preview = Arc::new(preview);
+                if let Preview::Listing(_) = preview.as_ref() {
+                    self.cache.insert(task.key.clone(), preview.clone());
                 }
+                self.apply_listing(task.key.path, &preview, selection);
+                return true;
             }
+
+            // a window past the end of the file isn't shown, but tells where the end is
+            if let Preview::Content(content) = &preview {
+                if content.total_lines.is_some() && task.key.path == self.curr_entry {
+                    self.total_lines = content.total_lines;
+                }
+            }
+            let preview = Arc::new(preview);
+            self.cache.insert(task.key.clone(), preview.clone());
+            self.preview = Some((task.key, preview));
+        }
+        true
+    }
+
+    /// starts generating the preview for [key](PreviewKey), unless it's already being generated
+    fn request_preview(&mut self, key: PreviewKey) {
+        if self.task.as_ref().is_some_and(|task| task.key == key) {
+            return;
         }
+        self.cancel_task();
+        self.task = Some(Preview::spawn(key));
     }
 
-    fn render_preview_pane_block(&mut self, area: Rect, buf: &mut Buffer) -> Rect {
+    fn cancel_task(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.cancel();
+        }
+    }
+
+    /// draws the block around the preview, with the [position](Option<String>) of the lines in view
+    /// on the top right
+    fn render_preview_pane_block(
+        &mut self,
+        area: Rect,
+        buf: &mut Buffer,
+        position: Option<String>,
+    ) {
         // block around preview pane
         let mut preview_pane_block_style = Style::default();
 
@@ -105,13 +448,15 @@ impl PreviewPane {
 
         let entry_permissions = Dir::get_entry_metadata_to_display(self.curr_entry.clone());
 
-        let preview_pane_block = Block::bordered()
+        let mut preview_pane_block = Block::bordered()
             .title(curr_selected_name)
             .title_bottom(Line::from(entry_permissions).centered())
             .style(preview_pane_block_style);
 
-        preview_pane_block.clone().render(area, buf);
-        // renders the preview pane inside the block
-        return preview_pane_block.inner(area);
+        if let Some(position) = position {
+            preview_pane_block = preview_pane_block.title(Line::from(position).right_aligned());
+        }
+
+        preview_pane_block.render(area, buf);
     }
 }
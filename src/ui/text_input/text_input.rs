@@ -0,0 +1,196 @@
+//! Single line text input, used by the popups that ask the user for a name or a path
+//!
+//! Supports the usual readline style editing: moving by character or word, deleting words and
+//! whole lines. Part of the text can be selected, in which case typing replaces it.
+//!
+//! # Example
+//! ```rust
+//! // "notes" is selected, so typing replaces it while keeping the extension
+//! let mut input = TextInput::new("notes.txt").with_selection(0, 5);
+//!
+//! input.handle_keys(key);
+//! println!("{}", input.get_value());
+//! ```
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::*;
+
+/// [value](Vec<char>): the text being edited
+/// [cursor](usize): position of the cursor, in chars, can be one past the last char
+/// [selection](Option<(usize, usize)>): start and end (exclusive) of the selected chars
+pub struct TextInput {
+    value: Vec<char>,
+    cursor: usize,
+    selection: Option<(usize, usize)>,
+}
+
+impl TextInput {
+    /// creates an input with [value](str) and the cursor at its end
+    pub fn new(value: &str) -> TextInput {
+        let value: Vec<char> = value.chars().collect();
+        TextInput {
+            cursor: value.len(),
+            value,
+            selection: None,
+        }
+    }
+
+    /// selects the chars from [start](usize) to [end](usize), leaving the cursor at the end
+    pub fn with_selection(mut self, start: usize, end: usize) -> TextInput {
+        let end = end.min(self.value.len());
+        let start = start.min(end);
+        self.selection = if start < end {
+            Some((start, end))
+        } else {
+            None
+        };
+        self.cursor = end;
+        self
+    }
+
+    pub fn get_value(&self) -> String {
+        self.value.iter().collect()
+    }
+
+    /// replaces the text, leaving the cursor at its end
+    pub fn set_value(&mut self, value: &str) {
+        *self = TextInput::new(value);
+    }
+
+    /// returns `true` if the key changed the input
+    pub fn handle_keys(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.move_to(0),
+            KeyCode::Char('e') if ctrl => self.move_to(self.value.len()),
+            KeyCode::Char('b') if ctrl => self.move_to(self.cursor.saturating_sub(1)),
+            KeyCode::Char('f') if ctrl => self.move_to(self.cursor + 1),
+            KeyCode::Char('b') if alt => self.move_to(self.get_prev_word()),
+            KeyCode::Char('f') if alt => self.move_to(self.get_next_word()),
+            // [Ctrl+w] deletes the word before the cursor
+            KeyCode::Char('w') if ctrl => self.delete_range(self.get_prev_word(), self.cursor),
+            KeyCode::Char('d') if alt => self.delete_range(self.cursor, self.get_next_word()),
+            // [Ctrl+u] deletes everything before the cursor, [Ctrl+k] everything after
+            KeyCode::Char('u') if ctrl => self.delete_range(0, self.cursor),
+            KeyCode::Char('k') if ctrl => self.delete_range(self.cursor, self.value.len()),
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(c) => self.insert(c),
+            KeyCode::Left if ctrl || alt => self.move_to(self.get_prev_word()),
+            KeyCode::Right if ctrl || alt => self.move_to(self.get_next_word()),
+            KeyCode::Left => match self.selection {
+                // leaves the cursor at the start of what was selected
+                Some((start, _)) => self.move_to(start),
+                None => self.move_to(self.cursor.saturating_sub(1)),
+            },
+            KeyCode::Right => match self.selection {
+                Some((_, end)) => self.move_to(end),
+                None => self.move_to(self.cursor + 1),
+            },
+            KeyCode::Home => self.move_to(0),
+            KeyCode::End => self.move_to(self.value.len()),
+            KeyCode::Backspace if ctrl || alt => {
+                self.delete_range(self.get_prev_word(), self.cursor)
+            }
+            KeyCode::Backspace => match self.selection {
+                Some((start, end)) => self.delete_range(start, end),
+                None => self.delete_range(self.cursor.saturating_sub(1), self.cursor),
+            },
+            KeyCode::Delete => match self.selection {
+                Some((start, end)) => self.delete_range(start, end),
+                None => self.delete_range(self.cursor, self.cursor + 1),
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn move_to(&mut self, position: usize) {
+        self.selection = None;
+        self.cursor = position.min(self.value.len());
+    }
+
+    /// types [c](char) at the cursor, replacing the selection if there is one
+    fn insert(&mut self, c: char) {
+        if let Some((start, end)) = self.selection {
+            self.delete_range(start, end);
+        }
+        self.value.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        let end = end.min(self.value.len());
+        let start = start.min(end);
+        self.value.drain(start..end);
+        self.selection = None;
+        self.cursor = start;
+    }
+
+    /// words are runs of alphanumeric chars, so `.`, `-`, `_`, `/` and spaces separate them
+    fn is_word_char(c: char) -> bool {
+        c.is_alphanumeric()
+    }
+
+    /// returns where the word before the cursor starts
+    fn get_prev_word(&self) -> usize {
+        let mut position = self.cursor;
+        // skips the separators right before the cursor, then the word itself
+        while position > 0 && !TextInput::is_word_char(self.value[position - 1]) {
+            position -= 1;
+        }
+        while position > 0 && TextInput::is_word_char(self.value[position - 1]) {
+            position -= 1;
+        }
+        position
+    }
+
+    /// returns where the word after the cursor ends
+    fn get_next_word(&self) -> usize {
+        let mut position = self.cursor;
+        while position < self.value.len() && !TextInput::is_word_char(self.value[position]) {
+            position += 1;
+        }
+        while position < self.value.len() && TextInput::is_word_char(self.value[position]) {
+            position += 1;
+        }
+        position
+    }
+}
+
+impl Widget for &TextInput {
+    /// renders the text with the selection highlighted and the cursor as a reversed char
+    /// the text is scrolled horizontally so the cursor is always visible
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = area.width as usize;
+        if width == 0 {
+            return;
+        }
+        let offset = (self.cursor + 1).saturating_sub(width);
+
+        let spans: Vec<Span> = self
+            .value
+            .iter()
+            .chain(std::iter::once(&' ')) // room for the cursor at the end
+            .enumerate()
+            .skip(offset)
+            .take(width)
+            .map(|(position, c)| {
+                let selected = self
+                    .selection
+                    .is_some_and(|(start, end)| position >= start && position < end);
+                let style = if position == self.cursor {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else if selected {
+                    Style::default().fg(Color::Black).bg(Color::Blue)
+                } else {
+                    Style::default()
+                };
+                Span::styled(c.to_string(), style)
+            })
+            .collect();
+
+        Line::from(spans).render(area, buf);
+    }
+}
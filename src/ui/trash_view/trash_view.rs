@@ -0,0 +1,295 @@
+//! Full screen view of the trash, where trashed entries can be restored or deleted for good
+//!
+//! Like the popups it takes over the terminal until closed, handling its own events
+//!
+//! # Example
+//! ```rust
+//! TrashView::show(jobs.clone());
+//! // entries may have been restored, so the file picker needs to be refreshed
+//! file_picker.initialize(None, None);
+//! ```
+
+use std::{collections::HashSet, io::Result, path::PathBuf};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    prelude::*,
+    widgets::{block::Position, Block, List, ListState, Paragraph},
+};
+
+use crate::{
+    theme::Theme,
+    tui,
+    ui::{
+        file_picker::{
+            dir::Dir,
+            file_ops::{ConflictResolution, FileOps},
+            jobs::{JobKind, Jobs},
+            trash::{Trash, TrashedEntry},
+        },
+        popup::popup::{show_confirmation, show_conflict, show_info},
+    },
+};
+
+/// [items](Vec<TrashedEntry>): entries in every trash directory
+/// [index](usize): the index of the entry under the cursor
+/// [marked](Vec<PathBuf>): trashed paths of the entries selected with space
+/// [jobs](Jobs): queue used to delete entries in the background
+/// [exit](bool): if the view should close
+pub struct TrashView {
+    items: Vec<TrashedEntry>,
+    index: usize,
+    marked: Vec<PathBuf>,
+    jobs: Jobs,
+    exit: bool,
+}
+
+impl TrashView {
+    /// shows the trash until the user closes it
+    pub fn show(jobs: Jobs) {
+        let mut view = TrashView {
+            items: Trash::list_entries(),
+            index: 0,
+            marked: Vec::new(),
+            jobs,
+            exit: false,
+        };
+
+        // try catch
+        if let Err(error) = view.run() {
+            show_info("Error displaying trash", error.to_string());
+        }
+    }
+
+    fn run(&mut self) -> Result<()> {
+        let mut term = tui::init()?;
+        // the popups draw over the view, so it has to be drawn from scratch after them
+        term.clear()?;
+
+        while !self.exit {
+            term.draw(|frame| self.render(frame.size(), frame.buffer_mut()))?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && self.handle_keys(key.code) {
+                    term.clear()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// returns `true` if a popup was shown
+    fn handle_keys(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => self.exit = true,
+            KeyCode::Char('j') => self.index = (self.index + 1).min(self.items.len().max(1) - 1),
+            KeyCode::Char('k') => self.index = self.index.saturating_sub(1),
+            KeyCode::Char('g') => self.index = 0,
+            KeyCode::Char('G') => self.index = self.items.len().max(1) - 1,
+            KeyCode::Char(' ') => self.mark_entry(),
+            KeyCode::Char('r') => {
+                self.restore_entries();
+                return true;
+            }
+            KeyCode::Char('d') => {
+                self.empty(false);
+                return true;
+            }
+            KeyCode::Char('D') => {
+                self.empty(true);
+                return true;
+            }
+            _ => (),
+        }
+        false
+    }
+
+    /// adds/removes the entry under the cursor to the marked entries
+    fn mark_entry(&mut self) {
+        if let Some(entry) = self.items.get(self.index) {
+            let path = entry.trashed_path.clone();
+            if self.marked.contains(&path) {
+                self.marked.retain(|marked| *marked != path);
+            } else {
+                self.marked.push(path);
+            }
+        }
+    }
+
+    /// returns the marked entries or, if none is marked, the entry under the cursor
+    fn get_selected(&self) -> Vec<&TrashedEntry> {
+        if self.marked.is_empty() {
+            self.items.get(self.index).into_iter().collect()
+        } else {
+            self.items
+                .iter()
+                .filter(|entry| self.marked.contains(&entry.trashed_path))
+                .collect()
+        }
+    }
+
+    /// moves the selected entries back where they were trashed from
+    /// asks the user what to do when something else is already there
+    fn restore_entries(&mut self) {
+        let mut apply_to_all: Option<ConflictResolution> = None;
+        let mut errors: Vec<String> = Vec::new();
+
+        for entry in self.get_selected() {
+            let mut dest = entry.original_path.clone();
+
+            if dest.symlink_metadata().is_ok() {
+                let resolution = match apply_to_all {
+                    Some(resolution) => resolution,
+                    None => {
+                        let info = Dir::get_shortened_path(dest.clone())
+                            + " already exists\n\ntrashed: "
+                            + &Trash::get_deletion_date_to_display(entry);
+                        let Some((resolution, to_all)) =
+                            show_conflict("Entry already exists", info)
+                        else {
+                            break; // the user cancelled the restore
+                        };
+                        if to_all {
+                            apply_to_all = Some(resolution);
+                        }
+                        resolution
+                    }
+                };
+
+                match resolution {
+                    ConflictResolution::Skip => continue,
+                    ConflictResolution::Rename => {
+                        dest = FileOps::get_free_name(&dest, &HashSet::new())
+                    }
+                    // what is in the way goes to the trash too, so overwriting loses nothing
+                    ConflictResolution::Overwrite => {
+                        if let Err(error) = Trash::trash_entry(dest.clone()) {
+                            errors.push(Dir::get_shortened_path(dest) + ": " + &error.to_string());
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            if let Err(error) = Trash::restore_entry(entry, &dest) {
+                errors.push(Dir::get_shortened_path(dest) + ": " + &error.to_string());
+            }
+        }
+
+        self.reload();
+
+        if !errors.is_empty() {
+            show_info("Error restoring entries", errors.join("\n"));
+        }
+    }
+
+    /// permanently deletes the selected entries, or all of them if [all](bool)
+    fn empty(&mut self, all: bool) {
+        let entries: Vec<&TrashedEntry> = match all {
+            true => self.items.iter().collect(),
+            false => self.get_selected(),
+        };
+
+        if entries.is_empty() {
+            return;
+        }
+
+        let info = match all {
+            true => "All ".to_string() + &entries.len().to_string() + " entries",
+            false => entries
+                .iter()
+                .map(|entry| Dir::get_shortened_path(entry.original_path.clone()))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        };
+
+        if show_confirmation("Delete permanently?", info) {
+            let paths: Vec<PathBuf> = entries
+                .iter()
+                .map(|entry| entry.trashed_path.clone())
+                .collect();
+
+            // the entries stop showing right away, even while the job is still deleting them
+            self.items
+                .retain(|item| !paths.contains(&item.trashed_path));
+            self.marked.clear();
+            self.index = self.index.min(self.items.len().max(1) - 1);
+
+            self.jobs.push(JobKind::EmptyTrash(paths));
+        }
+    }
+
+    /// reads the trash directories again
+    fn reload(&mut self) {
+        self.items = Trash::list_entries();
+        self.marked
+            .retain(|marked| self.items.iter().any(|item| item.trashed_path == *marked));
+        self.index = self.index.min(self.items.len().max(1) - 1);
+    }
+
+    fn render(&self, area: Rect, buf: &mut Buffer) {
+        let style = Theme::default();
+
+        let block = Block::bordered()
+            .title(" Trash ")
+            .title(
+                Line::from(" [r]estore  [d]elete  [D]elete all  [Space] mark  [q] close ")
+                    .centered(),
+            )
+            .title_position(Position::Top);
+
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if self.items.is_empty() {
+            Paragraph::new("Trash is empty")
+                .add_modifier(Modifier::DIM)
+                .render(inner, buf);
+            return;
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Fill(1), Constraint::Length(18)])
+            .split(inner);
+
+        // same look as the file picker, with the buffer symbol marking selected entries
+        let names = self.items.iter().map(|entry| {
+            let marker = if self.marked.contains(&entry.trashed_path) {
+                "-"
+            } else {
+                " "
+            };
+            marker.to_string()
+                + &Dir::get_display_name(entry.trashed_path.clone())[1..]
+                + "  "
+                + &Dir::get_shortened_path(entry.original_path.clone())
+        });
+
+        let dates = self.items.iter().map(Trash::get_deletion_date_to_display);
+
+        let mut state = ListState::default().with_selected(Some(self.index));
+
+        StatefulWidget::render(
+            List::new(names)
+                .style(style.normal)
+                .highlight_style(style.selected),
+            layout[0],
+            buf,
+            &mut state,
+        );
+
+        let mut state = ListState::default().with_selected(Some(self.index));
+
+        StatefulWidget::render(
+            List::new(dates)
+                .style(style.normal.add_modifier(Modifier::DIM))
+                .highlight_style(style.selected),
+            layout[1],
+            buf,
+            &mut state,
+        );
+    }
+}
@@ -1,3 +1,12 @@
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{Result, Write},
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 
 pub struct Utils {}
@@ -6,14 +15,19 @@ impl Utils {
     /// helper function to create a centered rect
     /// * [width](u16) in characters of text
     /// * [height](u16) in lines of text
+    ///
+    /// the rect is never bigger than [r](Rect), even if asked to
     pub fn centered_rect(width: u16, height: u16, r: Rect) -> Rect {
+        let width = width.min(r.width);
+        let height = height.min(r.height);
+
         // Cut the given rectangle into three vertical pieces
         let popup_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length((r.height - height) / 2),
+                Constraint::Length(r.height.saturating_sub(height) / 2),
                 Constraint::Length(height),
-                Constraint::Length((r.height - height) / 2),
+                Constraint::Length(r.height.saturating_sub(height) / 2),
             ])
             .split(r);
 
@@ -21,10 +35,78 @@ impl Utils {
         Layout::default()
             .direction(Direction::Horizontal)
             .constraints([
-                Constraint::Length((r.width - width) / 2),
+                Constraint::Length(r.width.saturating_sub(width) / 2),
                 Constraint::Length(width),
-                Constraint::Length((r.width - width) / 2),
+                Constraint::Length(r.width.saturating_sub(width) / 2),
             ])
             .split(popup_layout[1])[1] // Return the middle chunk
     }
+
+    /// returns `$XDG_DATA_HOME`, or its default `~/.local/share` if it's not set
+    /// tests get their own directory instead, so they never touch the user's trash or data files
+    pub fn get_data_home() -> PathBuf {
+        if cfg!(test) {
+            return env::temp_dir().join(format!("navfs-test-data-{}", process::id()));
+        }
+        match env::var("XDG_DATA_HOME") {
+            Ok(data_home) if !data_home.is_empty() => PathBuf::from(data_home),
+            _ => PathBuf::from(env::var("HOME").unwrap_or_default()).join(".local/share"),
+        }
+    }
+
+    /// returns the path of the file [name](str) navfs keeps its data in, inside
+    /// `$XDG_DATA_HOME/navfs`
+    pub fn get_data_file(name: &str) -> PathBuf {
+        Utils::get_data_home().join("navfs").join(name)
+    }
+
+    /// replaces the data file [name](str) with [content](str) at once, writing it to a temporary
+    /// file first so it's never left half written
+    ///
+    /// the temporary file is named after the process and a count of its writes, so writes from
+    /// different threads or navfs instances never share it
+    pub fn write_data_file(name: &str, content: &str) -> Result<()> {
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+
+        let file = Utils::get_data_file(name);
+        let temp = file.with_file_name(format!(
+            "{name}.{}-{}.tmp",
+            process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // poor man try catch
+        let res = (|| -> Result<()> {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp)?
+                .write_all(content.as_bytes())?;
+            fs::rename(&temp, &file)
+        })();
+        if res.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        res
+    }
+
+    /// formats an amount of bytes with a binary unit, ex: `1.5 MiB`
+    pub fn get_human_readable_size(bytes: u64) -> String {
+        let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+        let mut size = bytes as f64;
+        let mut unit = 0;
+
+        while size >= 1024.0 && unit < units.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+
+        match unit {
+            0 => format!("{bytes} B"),
+            _ => format!("{size:.1} {}", units[unit]),
+        }
+    }
 }